@@ -0,0 +1,321 @@
+use std::time::Duration;
+
+use bevy::prelude::{self, *};
+use bevy_rapier2d::prelude::Velocity;
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumIter};
+
+use crate::{
+    attribute::{self, Armor, Damage, DamageEvent, DashCooldown, Knockback, MaxHealth},
+    balance::Balance,
+    buff,
+    hostile::{HitStun, Hostile},
+    player::{ControlSet, Controls, Invulnerable, Player},
+    run::RunRng,
+    stats::RunStats,
+    ui::BLUE,
+    upgrade::Upgrades,
+    GameState,
+};
+
+pub struct Plugin;
+impl prelude::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Activate>()
+            .add_event::<Learn>()
+            .add_systems(
+                (
+                    tick_cooldowns,
+                    activate.after(ControlSet).after(tick_cooldowns),
+                    nova.after(activate),
+                    shield.after(activate),
+                    slow_time.after(activate),
+                    fade_nova_flashes,
+                )
+                    .in_set(OnUpdate(GameState::Game)),
+            )
+            // Learned alongside buff picks, before the next frame
+            .add_system(learn.in_base_set(CoreSet::PostUpdate));
+    }
+}
+
+#[derive(EnumIter, Clone, Copy, Debug, Display, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Ability {
+    Dash,
+    #[strum(serialize = "Nova Blast")]
+    Nova,
+    Shield,
+    #[strum(serialize = "Time Slow")]
+    TimeSlow,
+}
+
+/// Key triggering the ability in each slot, as many as the player can hold
+pub const KEYS: [KeyCode; 4] = [KeyCode::Space, KeyCode::Q, KeyCode::E, KeyCode::R];
+
+#[derive(Debug, Clone)]
+pub struct Slot {
+    pub ability: Ability,
+    /// Finished when the ability is ready
+    pub cooldown: Timer,
+}
+
+impl Slot {
+    pub fn ready(ability: Ability) -> Self {
+        let mut cooldown = Timer::new(Duration::ZERO, TimerMode::Once);
+        cooldown.tick(Duration::ZERO);
+        Self { ability, cooldown }
+    }
+}
+
+/// Abilities held, each triggered by the key of [`KEYS`] at its index
+#[derive(Debug, Default, Clone, Component)]
+pub struct Slots(pub Vec<Slot>);
+
+impl Slots {
+    /// Slots holding `abilities`, all ready
+    pub fn new(abilities: impl IntoIterator<Item = Ability>) -> Self {
+        Self(abilities.into_iter().map(Slot::ready).collect())
+    }
+
+    pub fn abilities(&self) -> impl Iterator<Item = Ability> + '_ {
+        self.0.iter().map(|slot| slot.ability)
+    }
+
+    pub fn has(&self, ability: Ability) -> bool {
+        self.abilities().any(|held| held == ability)
+    }
+}
+
+/// `user` triggered `ability`
+pub struct Activate {
+    pub ability: Ability,
+    pub user: Entity,
+}
+
+/// Put the ability offered by [`buff::Choices`] in this slot, replacing what it held.
+/// The slot past the last one adds a new slot.
+pub struct Learn(pub usize);
+
+/// Blast shown around the player on a nova, fading out as its timer runs
+#[derive(Debug, Clone, Component)]
+pub struct NovaFlash(pub Timer);
+
+/// Hostiles move slower until the timer finishes
+#[derive(Debug, Clone, Resource)]
+pub struct TimeSlow(pub Timer);
+
+fn cooldown(ability: Ability, dash_cooldown: &DashCooldown, balance: &Balance) -> Duration {
+    let seconds = match ability {
+        Ability::Dash => return dash_cooldown.0,
+        Ability::Nova => balance.nova_cooldown,
+        Ability::Shield => balance.shield_cooldown,
+        Ability::TimeSlow => balance.time_slow_cooldown,
+    };
+    Duration::from_secs_f32(seconds)
+}
+
+fn tick_cooldowns(
+    mut query: Query<(&mut Slots, &DashCooldown)>,
+    time: Res<Time>,
+    balance: Res<Balance>,
+) {
+    for (mut slots, dash_cooldown) in query.iter_mut() {
+        for slot in slots.0.iter_mut() {
+            let duration = cooldown(slot.ability, dash_cooldown, &balance);
+            slot.cooldown.set_duration(duration);
+            slot.cooldown.tick(time.delta());
+        }
+    }
+}
+
+/// Trigger the slot pressed this frame if it is ready
+pub fn activate(
+    controls: Res<Controls>,
+    mut player: Query<(Entity, &mut Slots), With<Player>>,
+    mut writer: EventWriter<Activate>,
+) {
+    let Some(index) = controls.ability else {
+        return;
+    };
+    let Ok((player, mut slots)) = player.get_single_mut() else {
+        return;
+    };
+    let Some(slot) = slots.0.get_mut(index) else {
+        return;
+    };
+    // Dashing needs a direction to go in
+    if !slot.cooldown.finished() || slot.ability == Ability::Dash && controls.movement == Vec2::ZERO
+    {
+        return;
+    }
+
+    slot.cooldown.reset();
+    writer.send(Activate {
+        ability: slot.ability,
+        user: player,
+    });
+}
+
+/// Damage and push back every hostile around the user
+#[allow(clippy::type_complexity)]
+fn nova(
+    mut commands: Commands,
+    mut events: EventReader<Activate>,
+    users: Query<(&Transform, &Damage, &Knockback)>,
+    mut hostiles: Query<(Entity, &Transform, &mut MaxHealth, &Armor, &mut Velocity), With<Hostile>>,
+    mut damage_writer: EventWriter<DamageEvent>,
+    balance: Res<Balance>,
+) {
+    for event in events.iter().filter(|event| event.ability == Ability::Nova) {
+        let Ok((origin, damage, knockback)) = users.get(event.user) else {
+            continue;
+        };
+        let origin = origin.translation;
+
+        for (hostile, transform, mut health, armor, mut velocity) in hostiles.iter_mut() {
+            let offset = (transform.translation - origin).truncate();
+            if offset.length() > balance.nova_radius {
+                continue;
+            }
+
+            let damage = (damage.0 as f32 * balance.nova_damage).round() as i32;
+            let amount = attribute::mitigate(damage, armor, &balance);
+            health.0 = health.0.saturating_sub(amount);
+            damage_writer.send(DamageEvent {
+                source: event.user,
+                target: hostile,
+                amount,
+                crit: false,
+            });
+
+            if knockback.0 > 0. {
+                velocity.linvel += offset.normalize_or_zero() * knockback.0;
+                commands.entity(hostile).insert(HitStun(Timer::from_seconds(
+                    balance.hit_stun,
+                    TimerMode::Once,
+                )));
+            }
+        }
+
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::from(BLUE).with_a(0.5),
+                    custom_size: Some(Vec2::splat(balance.nova_radius * 2.)),
+                    ..default()
+                },
+                transform: Transform::from_translation(origin - Vec3::Z),
+                ..default()
+            },
+            NovaFlash(Timer::from_seconds(balance.nova_flash, TimerMode::Once)),
+        ));
+    }
+}
+
+fn fade_nova_flashes(
+    mut commands: Commands,
+    mut flashes: Query<(Entity, &mut NovaFlash, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut flash, mut sprite) in flashes.iter_mut() {
+        flash.0.tick(time.delta());
+        if flash.0.finished() {
+            commands.entity(entity).despawn();
+        } else {
+            sprite.color.set_a(0.5 * flash.0.percent_left());
+        }
+    }
+}
+
+/// Make the user invulnerable for a while
+fn shield(
+    mut commands: Commands,
+    mut events: EventReader<Activate>,
+    users: Query<Option<&Invulnerable>>,
+    balance: Res<Balance>,
+) {
+    let duration = Duration::from_secs_f32(balance.shield_time);
+    for event in events
+        .iter()
+        .filter(|event| event.ability == Ability::Shield)
+    {
+        let Ok(invulnerable) = users.get(event.user) else {
+            continue;
+        };
+        // Never cut a longer invulnerability short
+        match invulnerable {
+            Some(invulnerable) if invulnerable.0.remaining() >= duration => {}
+            _ => {
+                commands
+                    .entity(event.user)
+                    .insert(Invulnerable(Timer::new(duration, TimerMode::Once)));
+            }
+        }
+    }
+}
+
+/// Start slowing hostiles down on activation, and stop once the time is up
+fn slow_time(
+    mut commands: Commands,
+    mut events: EventReader<Activate>,
+    slow: Option<ResMut<TimeSlow>>,
+    time: Res<Time>,
+    balance: Res<Balance>,
+) {
+    if events
+        .iter()
+        .any(|event| event.ability == Ability::TimeSlow)
+    {
+        commands.insert_resource(TimeSlow(Timer::from_seconds(
+            balance.time_slow_time,
+            TimerMode::Once,
+        )));
+        return;
+    }
+
+    if let Some(mut slow) = slow {
+        slow.0.tick(time.delta());
+        if slow.0.finished() {
+            commands.remove_resource::<TimeSlow>();
+        }
+    }
+}
+
+fn learn(
+    mut reader: EventReader<Learn>,
+    mut player: Query<&mut Slots, With<Player>>,
+    mut choices: ResMut<buff::Choices>,
+    mut stats: ResMut<RunStats>,
+    mut rng: ResMut<RunRng>,
+    balance: Res<Balance>,
+    upgrades: Res<Upgrades>,
+) {
+    for &Learn(index) in reader.iter() {
+        let (Some(ability), Ok(mut slots)) = (choices.ability, player.get_single_mut()) else {
+            continue;
+        };
+        if choices.remaining == 0
+            || slots.has(ability)
+            || index > slots.0.len()
+            || index >= KEYS.len()
+        {
+            continue;
+        }
+
+        match slots.0.get_mut(index) {
+            Some(slot) => *slot = Slot::ready(ability),
+            None => slots.0.push(Slot::ready(ability)),
+        }
+        stats.abilities.push(ability);
+        choices.remaining -= 1;
+        choices.picked += 1;
+        let held: Vec<_> = slots.abilities().collect();
+        choices.randomize(
+            3,
+            balance.ability_chance,
+            &upgrades,
+            &held,
+            &mut rng.choices,
+        );
+    }
+}
@@ -7,8 +7,12 @@ use bevy::{
 use bevy_rapier2d::prelude::RapierConfiguration;
 
 use crate::{
-    buff,
-    hostile::{Score, SpawnRate},
+    ability::TimeSlow,
+    balance::{self, Balance},
+    buff::{self, Affect},
+    hostile::{Hostile, Score, SpawnRate},
+    loot,
+    modifier::{Modifiers, Timed},
     projectile::ProjectileSpeed,
     GameState,
 };
@@ -16,9 +20,20 @@ use crate::{
 pub struct Plugin;
 impl prelude::Plugin for Plugin {
     fn build(&self, app: &mut App) {
-        app.add_systems((update_timers, increase_difficulty).in_set(OnUpdate(GameState::Game)))
-            .add_system(update_timer_with_attack_speed)
-            .add_system(level_up)
+        app.add_event::<LevelUpEvent>()
+            .add_event::<DamageEvent>()
+            .add_systems(
+                (
+                    update_timers,
+                    update_timer_with_attack_speed,
+                    increase_difficulty,
+                    regenerate,
+                )
+                    .in_set(OnUpdate(GameState::Game)),
+            )
+            // Replays need the experience of a frame counted within that frame
+            .add_system(level_up.after(loot::pickup_loot))
+            .add_system(lifesteal)
             .add_system(max_health)
             .add_system(freeze_all_movement);
     }
@@ -42,34 +57,136 @@ pub struct AttackSpeedTimer(pub Timer);
 pub struct AttackSpeed(pub Duration);
 #[derive(Debug, Clone, Component)]
 pub struct AttackRange(pub f32);
+/// Health gained per second
+#[derive(Default, Component)]
+pub struct HealthRegen(pub f32);
+/// Fraction of damage dealt gained as health
+#[derive(Default, Component)]
+pub struct Lifesteal(pub f32);
+/// Cuts incoming damage by `armor / (armor + armor_scale)`, see [`Balance`]
+#[derive(Default, Component)]
+pub struct Armor(pub f32);
+/// Chance of a hit dealing [`CritMultiplier`] times its damage
+#[derive(Default, Component)]
+pub struct CritChance(pub f32);
+#[derive(Default, Component)]
+pub struct CritMultiplier(pub f32);
+/// Speed hits push targets back with
+#[derive(Default, Component)]
+pub struct Knockback(pub f32);
+/// Distance from which loot is pulled towards the player
+#[derive(Default, Component)]
+pub struct PickupRadius(pub f32);
+/// Time hits are ignored for after taking one
+#[derive(Debug, Clone, Component)]
+pub struct Invulnerability(pub Duration);
+/// Distance a dash carries the player
+#[derive(Default, Component)]
+pub struct DashDistance(pub f32);
+#[derive(Debug, Clone, Component)]
+pub struct DashCooldown(pub Duration);
+/// Healing that hasn't added up to a whole point of [`Health`] yet
+#[derive(Default, Component)]
+pub struct PartialHealth(pub f32);
+/// Level reached, starting at 1
+#[derive(Component)]
+pub struct Level(pub u32);
+
+impl Default for Level {
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
 #[derive(Default, Component)]
 pub struct Experience {
     pub current: u32,
     pub cap: u32,
 }
 
-pub fn insert_common(commands: &mut EntityCommands) {
+/// Attributes shared by the player and hostiles
+pub fn insert_common(commands: &mut EntityCommands, stats: &balance::Stats) {
+    insert_stats(commands, stats, Modifiers::new(base(stats)));
+}
+
+/// Attributes of the player, those of [`insert_common`] plus its own
+pub fn insert_player(
+    commands: &mut EntityCommands,
+    stats: &balance::Stats,
+    player: &balance::PlayerStats,
+) {
+    let modifiers = Modifiers::new(base(stats).into_iter().chain([
+        (Affect::PickupRadius, player.pickup_radius),
+        (Affect::Invulnerability, player.invulnerability),
+        (Affect::DashDistance, player.dash_distance),
+        (Affect::DashCooldown, player.dash_cooldown),
+    ]));
+    insert_stats(commands, stats, modifiers);
     commands.insert((
-        Damage(35),
-        AttackRange(800.),
-        AttackSpeedTimer(Timer::from_seconds(0.5, TimerMode::Once)),
-        AttackSpeed(Duration::from_millis(500)),
-        ProjectileSpeed(500.),
-        MoveSpeed(2.5),
-        MaxHealth(100),
-        Health(100),
-        HealChance(0.1),
-        DupChance(0.25),
+        PickupRadius(player.pickup_radius),
+        Invulnerability(Duration::from_secs_f32(player.invulnerability)),
+        DashDistance(player.dash_distance),
+        DashCooldown(Duration::from_secs_f32(player.dash_cooldown)),
     ));
 }
 
+/// Modifier base of every attribute in `stats`
+fn base(stats: &balance::Stats) -> [(Affect, f32); 14] {
+    [
+        (Affect::Damage, stats.damage as f32),
+        (Affect::AttackRange, stats.attack_range),
+        (Affect::AttackSpeed, stats.attack_speed),
+        (Affect::ProjectileSpeed, stats.projectile_speed),
+        (Affect::MoveSpeed, stats.move_speed),
+        (Affect::MaxHealth, stats.max_health as f32),
+        (Affect::DropHealth, stats.heal_chance),
+        (Affect::DupChance, stats.dup_chance),
+        (Affect::HealthRegen, stats.health_regen),
+        (Affect::Lifesteal, stats.lifesteal),
+        (Affect::Armor, stats.armor),
+        (Affect::CritChance, stats.crit_chance),
+        (Affect::CritMultiplier, stats.crit_multiplier),
+        (Affect::Knockback, stats.knockback),
+    ]
+}
+
+fn insert_stats(commands: &mut EntityCommands, stats: &balance::Stats, modifiers: Modifiers) {
+    commands
+        .insert((
+            Damage(stats.damage),
+            AttackRange(stats.attack_range),
+            AttackSpeedTimer(Timer::from_seconds(stats.attack_speed, TimerMode::Once)),
+            AttackSpeed(Duration::from_secs_f32(stats.attack_speed)),
+            ProjectileSpeed(stats.projectile_speed),
+            MoveSpeed(stats.move_speed),
+            MaxHealth(stats.max_health),
+            Health(stats.max_health),
+            HealChance(stats.heal_chance),
+            DupChance(stats.dup_chance),
+            HealthRegen(stats.health_regen),
+            Lifesteal(stats.lifesteal),
+            Armor(stats.armor),
+            CritChance(stats.crit_chance),
+            CritMultiplier(stats.crit_multiplier),
+        ))
+        .insert((
+            Knockback(stats.knockback),
+            PartialHealth::default(),
+            modifiers,
+            Timed::default(),
+        ));
+}
+
 fn update_timer_with_attack_speed(
-    mut query: Query<(&mut AttackSpeedTimer, &AttackSpeed)>,
+    mut query: Query<(&mut AttackSpeedTimer, &AttackSpeed, Option<&Hostile>)>,
     time: Res<Time>,
+    slow: Option<Res<TimeSlow>>,
+    balance: Res<Balance>,
 ) {
-    for (mut timer, attack_speed) in query.iter_mut() {
+    for (mut timer, attack_speed, hostile) in query.iter_mut() {
         timer.0.set_duration(attack_speed.0);
-        timer.0.tick(time.delta());
+        let slowed = slow.is_some() && hostile.is_some();
+        timer.0.tick(elapsed(&time, slowed, &balance));
     }
 }
 
@@ -78,36 +195,119 @@ fn freeze_all_movement(mut config: ResMut<RapierConfiguration>, game_state: Res<
         return;
     }
 
-    if game_state.0 != GameState::Game {
-        config.physics_pipeline_active = false;
-    } else {
-        config.physics_pipeline_active = true;
+    config.physics_pipeline_active = game_state.0 == GameState::Game;
+}
+
+fn update_timers(
+    mut query: Query<(&mut AttackSpeedTimer, Option<&Hostile>)>,
+    time: Res<Time>,
+    slow: Option<Res<TimeSlow>>,
+    balance: Res<Balance>,
+) {
+    for (mut timer, hostile) in query.iter_mut() {
+        let slowed = slow.is_some() && hostile.is_some();
+        timer.0.tick(elapsed(&time, slowed, &balance));
     }
 }
 
-fn update_timers(mut query: Query<&mut AttackSpeedTimer>, time: Res<Time>) {
-    for mut timer in query.iter_mut() {
-        timer.0.tick(time.delta());
+/// Time passed this frame, for hostiles `slowed` down by a [`TimeSlow`] too
+fn elapsed(time: &Time, slowed: bool, balance: &Balance) -> Duration {
+    if slowed {
+        time.delta().mul_f32(balance.time_slow_factor)
+    } else {
+        time.delta()
     }
 }
 
 pub struct LevelUpEvent();
 
+/// `source` took `amount` of `target`'s health
+pub struct DamageEvent {
+    pub source: Entity,
+    pub target: Entity,
+    pub amount: i32,
+    pub crit: bool,
+}
+
+/// Grant every level the collected experience pays for, each one offering a choice
 fn level_up(
-    mut query: Query<&mut Experience, Changed<Experience>>,
+    mut query: Query<(&mut Experience, &mut Level), Changed<Experience>>,
     mut choices: ResMut<buff::Choices>,
     mut ev_levelup: EventWriter<LevelUpEvent>,
     mut game_state: ResMut<NextState<GameState>>,
+    balance: Res<Balance>,
 ) {
-    for mut experience in query.iter_mut() {
-        if experience.current >= experience.cap {
+    for (mut experience, mut level) in query.iter_mut() {
+        if experience.current < experience.cap {
+            continue;
+        }
+
+        while experience.current >= experience.cap {
             experience.current -= experience.cap;
-            experience.cap += 100;
+            level.0 += 1;
+            experience.cap = balance.experience.cap(level.0);
 
             ev_levelup.send(LevelUpEvent());
-
             choices.remaining += 1;
-            game_state.set(GameState::LevelUp);
+        }
+        game_state.set(GameState::LevelUp);
+    }
+}
+
+/// Damage left of a `damage` hit once `armor` has blocked a flat amount and absorbed
+/// its share of the rest. Hits always deal at least 1.
+pub fn mitigate(damage: i32, armor: &Armor, balance: &Balance) -> i32 {
+    let armor = armor.0.max(0.);
+    let blocked = damage as f32 - armor * balance.armor_flat;
+    let reduction = armor / (armor + balance.armor_scale);
+    ((blocked * (1. - reduction)).round() as i32).max(1)
+}
+
+/// Add `amount` of healing, crediting [`Health`] in whole points up to [`MaxHealth`]
+fn heal(health: &mut Health, max_health: &MaxHealth, partial: &mut PartialHealth, amount: f32) {
+    if health.0 <= 0 || health.0 >= max_health.0 {
+        partial.0 = 0.;
+        return;
+    }
+
+    partial.0 += amount;
+    let whole = partial.0.trunc();
+    partial.0 -= whole;
+    health.0 = (health.0 + whole as i32).min(max_health.0);
+}
+
+fn regenerate(
+    mut query: Query<(&HealthRegen, &mut Health, &MaxHealth, &mut PartialHealth)>,
+    time: Res<Time>,
+) {
+    for (regen, mut health, max_health, mut partial) in query.iter_mut() {
+        if regen.0 > 0. {
+            heal(
+                &mut health,
+                max_health,
+                &mut partial,
+                regen.0 * time.delta_seconds(),
+            );
+        }
+    }
+}
+
+fn lifesteal(
+    mut events: EventReader<DamageEvent>,
+    mut query: Query<(&Lifesteal, &mut Health, &MaxHealth, &mut PartialHealth)>,
+) {
+    for event in events.iter() {
+        let Ok((lifesteal, mut health, max_health, mut partial)) = query.get_mut(event.source)
+        else {
+            continue;
+        };
+        if lifesteal.0 > 0. {
+            heal(
+                &mut health,
+                max_health,
+                &mut partial,
+                event.amount as f32 * lifesteal.0,
+            );
         }
     }
 }
@@ -120,14 +320,15 @@ fn max_health(mut query: Query<(&mut Health, &MaxHealth)>) {
     }
 }
 
-fn increase_difficulty(mut spawn_rate: ResMut<SpawnRate>, score: Res<Score>) {
-    if !score.is_changed() {
+fn increase_difficulty(
+    mut spawn_rate: ResMut<SpawnRate>,
+    score: Res<Score>,
+    balance: Res<Balance>,
+) {
+    if !score.is_changed() && !balance.is_changed() {
         return;
     }
 
-    let base_duration = 1.;
-    let score_factor = 0.003;
-
-    let new_duration = base_duration / (1.0 + score_factor * score.0 as f32);
+    let new_duration = balance.spawn_base / (1.0 + balance.score_factor * score.0 as f32);
     spawn_rate.0 = Duration::from_secs_f32(new_duration);
 }
@@ -4,7 +4,7 @@ use bevy_kira_audio::{prelude::AudioEmitter, AudioControl, *};
 pub struct Plugin;
 impl prelude::Plugin for Plugin {
     fn build(&self, app: &mut App) {
-        app.add_system(play_sfx);
+        app.init_resource::<Volume>().add_system(play_sfx);
         app.add_audio_channel::<BgmChannel>();
         app.add_audio_channel::<ShootingChannel>();
         app.add_audio_channel::<EnemyChannel>();
@@ -12,6 +12,16 @@ impl prelude::Plugin for Plugin {
     }
 }
 
+/// Master volume multiplier applied on top of every channel's own level.
+#[derive(Debug, Clone, Resource)]
+pub struct Volume(pub f64);
+
+impl Default for Volume {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
 #[derive(Default, Component)]
 pub struct AudioPlayer;
 
@@ -37,20 +47,22 @@ fn play_sfx(
     mut ev_levelup: EventReader<crate::attribute::LevelUpEvent>,
     mut ev_enemy_death: EventReader<crate::hostile::EnemyDeathEvent>,
     mut ev_shoot: EventReader<crate::projectile::ShootEvent>,
+    mut ev_damage: EventReader<crate::attribute::DamageEvent>,
     mut ev_ui_select: EventReader<crate::ui::UiClickedEvent>,
     mut ev_upgrade_select: EventReader<crate::ui::UpgradeSelectedEvent>,
+    volume: Res<Volume>,
     // mut ev_ui_select: EventReader<crate::CRATENAME::UiClickEvent>,
     // mut ev_ui_nav: EventReader<crate::CRATENAME::UiNavigateEvent>,
 ) {
     for _ev in ev_levelup.iter() {
-        ui_audio.set_volume(0.03);
+        ui_audio.set_volume(0.03 * volume.0);
 
         let sfx = asset_server.load("ui_level_up.wav");
         ui_audio.play(sfx);
     }
 
     for ev in ev_enemy_death.iter() {
-        enemy_audio.set_volume(0.045);
+        enemy_audio.set_volume(0.045 * volume.0);
 
         let enemy_loc = ev.0;
 
@@ -69,23 +81,30 @@ fn play_sfx(
     }
 
     for _ev in ev_shoot.iter() {
-        shooting_audio.set_volume(0.01);
+        shooting_audio.set_volume(0.01 * volume.0);
 
         let sfx = asset_server.load("projectile_01.wav");
         let _death_sound = shooting_audio.play(sfx).handle();
     }
 
+    for _ev in ev_damage.iter().filter(|ev| ev.crit) {
+        shooting_audio.set_volume(0.02 * volume.0);
+
+        let sfx = asset_server.load("projectile_02.wav");
+        shooting_audio.play(sfx);
+    }
+
     // Create event for UI "Select"
     for _ev in ev_upgrade_select.iter() {
         let sfx = asset_server.load("drink_potion.wav");
-        ui_audio.set_volume(0.03);
+        ui_audio.set_volume(0.03 * volume.0);
         ui_audio.play(sfx);
     }
 
     // Create event for UI "Navigation"
     for _ev in ev_ui_select.iter() {
         let sfx = asset_server.load("ui_menu_click.wav");
-        ui_audio.set_volume(0.01);
+        ui_audio.set_volume(0.01 * volume.0);
         ui_audio.play(sfx);
     }
 }
@@ -0,0 +1,454 @@
+use std::{collections::BTreeMap, ops::Range};
+
+use bevy::{
+    prelude::{self, *},
+    reflect::TypeUuid,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    buff::Affect,
+    ron_asset::{self, RonAsset},
+};
+
+pub struct Plugin;
+impl prelude::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(ron_asset::Plugin::<Balance>::default());
+    }
+}
+
+/// Tuning numbers of the game, read from `assets/game.balance.ron` and reloaded whenever the
+/// file changes. Starting stats only apply to entities spawned after a reload.
+#[derive(Debug, Clone, Resource, Serialize, Deserialize, TypeUuid)]
+#[uuid = "6a0c4a57-3f0e-4d8e-9a52-7c1b0f3e52d1"]
+#[serde(default)]
+pub struct Balance {
+    pub player: Stats,
+    pub player_only: PlayerStats,
+    pub hostile: Stats,
+    /// Distance from the player at which hostiles spawn
+    pub spawn_distance: f32,
+    /// Speed hostiles chase the player at
+    pub hostile_speed: f32,
+    /// Experience loot dropped by every kill
+    pub experience_drop: Range<u32>,
+    /// Health loot dropped when a kill rolls under the player's `HealChance`
+    pub health_drop: Range<u32>,
+    /// Chance of a kill dropping haste loot
+    pub haste_chance: f32,
+    /// Movement speed gained from haste, `1.0` doubles it
+    pub haste_boost: f32,
+    /// Seconds haste lasts
+    pub haste_duration: f32,
+    /// Seconds between two spawns at the start of a run
+    pub spawn_base: f32,
+    /// How quickly the spawn rate grows with the score
+    pub score_factor: f32,
+    /// Experience needed for each level up
+    pub experience: Curve,
+    /// Armor halving incoming damage
+    pub armor_scale: f32,
+    /// Damage blocked outright by each point of armor, before the share it absorbs
+    pub armor_flat: f32,
+    /// Speed loot is pulled in at, as a multiple of the player's movement speed
+    pub loot_magnet: f32,
+    /// Seconds a dash lasts, the player can't be hit meanwhile
+    pub dash_time: f32,
+    /// Chance of a level up also offering an ability
+    pub ability_chance: f64,
+    /// Distance the nova blast reaches
+    pub nova_radius: f32,
+    /// Nova blast damage as a multiple of the player's damage
+    pub nova_damage: f32,
+    /// Seconds the nova blast stays visible
+    pub nova_flash: f32,
+    /// Seconds between two nova blasts
+    pub nova_cooldown: f32,
+    /// Seconds the shield keeps the player from being hit
+    pub shield_time: f32,
+    /// Seconds between two shields
+    pub shield_cooldown: f32,
+    /// Hostile movement and attack speed while time is slowed, `0.5` halves them
+    pub time_slow_factor: f32,
+    /// Seconds time stays slowed
+    pub time_slow_time: f32,
+    /// Seconds between two time slows
+    pub time_slow_cooldown: f32,
+    /// Seconds a knocked back hostile stops chasing the player
+    pub hit_stun: f32,
+    /// Upgrades to these attributes add a diff's value times the scale rather than a
+    /// percentage, as they start at or near zero. Attributes left out of the file keep
+    /// their default scale.
+    #[serde(deserialize_with = "over_default_flat_upgrades")]
+    pub flat_upgrades: BTreeMap<Affect, f32>,
+    /// Bounds kept by every attribute however it is modified. Bounds set in the file
+    /// override the defaults one by one, the others stay in place.
+    #[serde(deserialize_with = "over_default_limits")]
+    pub limits: BTreeMap<Affect, Limit>,
+}
+
+/// Starting attributes, see [`crate::attribute::insert_common`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    pub damage: i32,
+    pub attack_range: f32,
+    /// Seconds between two attacks
+    pub attack_speed: f32,
+    pub projectile_speed: f32,
+    /// Pixels per second
+    pub move_speed: f32,
+    pub max_health: i32,
+    pub heal_chance: f32,
+    pub dup_chance: f32,
+    /// Health gained per second
+    pub health_regen: f32,
+    /// Fraction of damage dealt gained as health
+    pub lifesteal: f32,
+    pub armor: f32,
+    pub crit_chance: f32,
+    pub crit_multiplier: f32,
+    /// Speed hits push targets back with
+    pub knockback: f32,
+}
+
+/// Starting attributes hostiles don't have, see [`crate::attribute::insert_player`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerStats {
+    /// Distance from which loot is pulled in
+    pub pickup_radius: f32,
+    /// Seconds hits are ignored for after taking one
+    pub invulnerability: f32,
+    pub dash_distance: f32,
+    /// Seconds between two dashes
+    pub dash_cooldown: f32,
+}
+
+impl Default for Balance {
+    fn default() -> Self {
+        Self {
+            player: Stats {
+                knockback: 150.,
+                ..default()
+            },
+            player_only: default(),
+            hostile: default(),
+            spawn_distance: 1000.,
+            hostile_speed: 100.,
+            experience_drop: 10..30,
+            health_drop: 1..20,
+            haste_chance: 0.02,
+            haste_boost: 1.,
+            haste_duration: 10.,
+            spawn_base: 1.,
+            score_factor: 0.003,
+            experience: Curve::Linear {
+                first: 100,
+                step: 100,
+            },
+            armor_scale: 100.,
+            armor_flat: 0.1,
+            // 1000 pixels per second at the starting movement speed
+            loot_magnet: 1000. / 150.,
+            dash_time: 0.15,
+            ability_chance: 0.25,
+            nova_radius: 250.,
+            nova_damage: 2.,
+            nova_flash: 0.3,
+            nova_cooldown: 8.,
+            shield_time: 2.,
+            shield_cooldown: 12.,
+            time_slow_factor: 0.3,
+            time_slow_time: 4.,
+            time_slow_cooldown: 15.,
+            hit_stun: 0.15,
+            flat_upgrades: default_flat_upgrades(),
+            limits: default_limits(),
+        }
+    }
+}
+
+fn default_flat_upgrades() -> BTreeMap<Affect, f32> {
+    BTreeMap::from([
+        // Health per second
+        (Affect::HealthRegen, 10.),
+        // Fraction of damage dealt
+        (Affect::Lifesteal, 1.),
+        (Affect::Armor, 100.),
+        // Chance per hit
+        (Affect::CritChance, 0.2),
+    ])
+}
+
+fn default_limits() -> BTreeMap<Affect, Limit> {
+    BTreeMap::from([
+        (Affect::MaxHealth, Limit::min(1.)),
+        (Affect::Damage, Limit::min(1.)),
+        (Affect::AttackRange, Limit::min(10.)),
+        (
+            Affect::AttackSpeed,
+            Limit {
+                min: Some(0.05),
+                soft_min: Some(0.2),
+                ..default()
+            },
+        ),
+        (Affect::MoveSpeed, Limit::min(30.)),
+        (Affect::ProjectileSpeed, Limit::min(100.)),
+        (
+            Affect::DupChance,
+            Limit {
+                min: Some(0.),
+                max: Some(0.9),
+                soft_max: Some(0.5),
+                ..default()
+            },
+        ),
+        (
+            Affect::DropHealth,
+            Limit {
+                min: Some(0.),
+                max: Some(1.),
+                ..default()
+            },
+        ),
+        (Affect::HealthRegen, Limit::min(0.)),
+        (Affect::Armor, Limit::min(0.)),
+        (
+            Affect::CritChance,
+            Limit {
+                min: Some(0.),
+                max: Some(1.),
+                ..default()
+            },
+        ),
+        (Affect::CritMultiplier, Limit::min(1.)),
+        (Affect::Knockback, Limit::min(0.)),
+        (Affect::PickupRadius, Limit::min(0.)),
+        (Affect::DashDistance, Limit::min(0.)),
+        (Affect::DashCooldown, Limit::min(0.25)),
+        (
+            Affect::Invulnerability,
+            Limit {
+                min: Some(0.),
+                max: Some(3.),
+                ..default()
+            },
+        ),
+        (
+            Affect::Lifesteal,
+            Limit {
+                min: Some(0.),
+                max: Some(0.5),
+                ..default()
+            },
+        ),
+    ])
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self {
+            damage: 35,
+            attack_range: 800.,
+            attack_speed: 0.5,
+            projectile_speed: 500.,
+            move_speed: 150.,
+            max_health: 100,
+            heal_chance: 0.1,
+            dup_chance: 0.25,
+            health_regen: 0.,
+            lifesteal: 0.,
+            armor: 0.,
+            crit_chance: 0.05,
+            crit_multiplier: 1.5,
+            knockback: 0.,
+        }
+    }
+}
+
+impl Default for PlayerStats {
+    fn default() -> Self {
+        Self {
+            pickup_radius: 150.,
+            invulnerability: 0.5,
+            dash_distance: 200.,
+            dash_cooldown: 2.,
+        }
+    }
+}
+
+/// Experience needed to advance past a level, runs start at level 1
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Curve {
+    /// `first` for level 1, `step` more for every level after
+    Linear { first: u32, step: u32 },
+    /// Like [`Curve::Linear`] plus `growth` times the square of the levels past the first
+    Quadratic { first: u32, step: u32, growth: u32 },
+    /// Listed per level, the last entry repeating past the end
+    Table(Vec<u32>),
+}
+
+impl Curve {
+    pub fn cap(&self, level: u32) -> u32 {
+        let n = level.saturating_sub(1);
+        let cap = match self {
+            Curve::Linear { first, step } => first.saturating_add(step.saturating_mul(n)),
+            Curve::Quadratic {
+                first,
+                step,
+                growth,
+            } => first
+                .saturating_add(step.saturating_mul(n))
+                .saturating_add(growth.saturating_mul(n.saturating_mul(n))),
+            Curve::Table(caps) => caps
+                .get(n as usize)
+                .or(caps.last())
+                .copied()
+                .unwrap_or(u32::MAX),
+        };
+        // A free level would be granted forever
+        cap.max(1)
+    }
+}
+
+/// Bounds of an attribute. Past a soft bound gains shrink logarithmically, past a hard
+/// bound they stop.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Limit {
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    pub soft_min: Option<f32>,
+    pub soft_max: Option<f32>,
+}
+
+impl Limit {
+    fn min(min: f32) -> Self {
+        Self {
+            min: Some(min),
+            ..default()
+        }
+    }
+
+    /// Bounds of `self`, falling back to those of `other` where unset
+    fn or(self, other: Self) -> Self {
+        Self {
+            min: self.min.or(other.min),
+            max: self.max.or(other.max),
+            soft_min: self.soft_min.or(other.soft_min),
+            soft_max: self.soft_max.or(other.soft_max),
+        }
+    }
+
+    pub fn apply(&self, mut value: f32) -> f32 {
+        if let Some(soft) = self.soft_max.filter(|&soft| soft > 0. && value > soft) {
+            value = soft * (1. + (value / soft).ln());
+        }
+        if let Some(soft) = self.soft_min.filter(|&soft| value > 0. && value < soft) {
+            value = soft / (1. + (soft / value).ln());
+        }
+        if let Some(max) = self.max {
+            value = value.min(max);
+        }
+        if let Some(min) = self.min {
+            value = value.max(min);
+        }
+        value
+    }
+}
+
+fn over_default_flat_upgrades<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<Affect, f32>, D::Error> {
+    let mut scales = default_flat_upgrades();
+    scales.extend(BTreeMap::<Affect, f32>::deserialize(deserializer)?);
+    Ok(scales)
+}
+
+fn over_default_limits<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<Affect, Limit>, D::Error> {
+    let mut limits = default_limits();
+    for (affect, limit) in BTreeMap::<Affect, Limit>::deserialize(deserializer)? {
+        let merged = limits
+            .get(&affect)
+            .map_or(limit, |&default| limit.or(default));
+        limits.insert(affect, merged);
+    }
+    Ok(limits)
+}
+
+impl Balance {
+    pub fn limit(&self, affect: Affect) -> Limit {
+        self.limits.get(&affect).copied().unwrap_or_default()
+    }
+}
+
+impl RonAsset for Balance {
+    const PATH: &'static str = "game.balance.ron";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_balance_parses() {
+        let balance: Balance = ron::from_str(include_str!("../assets/game.balance.ron")).unwrap();
+        assert_eq!(balance.experience_drop, Balance::default().experience_drop);
+    }
+
+    #[test]
+    fn curves_grow_per_level() {
+        let linear = Curve::Linear {
+            first: 100,
+            step: 50,
+        };
+        assert_eq!([1, 2, 3].map(|level| linear.cap(level)), [100, 150, 200]);
+
+        let quadratic = Curve::Quadratic {
+            first: 100,
+            step: 50,
+            growth: 10,
+        };
+        assert_eq!([1, 2, 3].map(|level| quadratic.cap(level)), [100, 160, 240]);
+
+        let table = Curve::Table(vec![10, 0, 30]);
+        assert_eq!([1, 2, 3, 9].map(|level| table.cap(level)), [10, 1, 30, 30]);
+    }
+
+    #[test]
+    fn limits_diminish_then_stop() {
+        let limit = Limit {
+            max: Some(0.9),
+            soft_max: Some(0.5),
+            ..default()
+        };
+        assert_eq!(limit.apply(0.4), 0.4);
+        assert!(limit.apply(0.6) < 0.6 && limit.apply(0.6) > 0.5);
+        assert_eq!(limit.apply(10.), 0.9);
+
+        let limit = Limit {
+            min: Some(0.05),
+            soft_min: Some(0.2),
+            ..default()
+        };
+        assert!(limit.apply(0.1) > 0.1 && limit.apply(0.1) < 0.2);
+        assert_eq!(limit.apply(0.), 0.05);
+    }
+
+    #[test]
+    fn configured_limits_keep_default_floors() {
+        let balance: Balance = ron::from_str(
+            "(limits: { MaxHealth: (max: Some(500.0)), Lifesteal: (max: Some(0.2)) })",
+        )
+        .unwrap();
+        assert_eq!(balance.limit(Affect::MaxHealth).apply(0.), 1.);
+        assert_eq!(balance.limit(Affect::MaxHealth).apply(900.), 500.);
+        assert_eq!(balance.limit(Affect::Lifesteal).apply(1.), 0.2);
+        assert_eq!(balance.limit(Affect::Damage).apply(-5.), 1.);
+    }
+}
@@ -1,15 +1,19 @@
-use std::{ops::Sub, time::Duration};
+use std::{ops::Neg, time::Duration};
 
 use bevy::prelude::{self, *};
 use bevy_turborand::prelude::*;
+use serde::{Deserialize, Serialize};
 use strum::{Display, EnumIter, IntoEnumIterator};
 
 use crate::{
-    attribute::{
-        AttackRange, AttackSpeed, Damage, DupChance, HealChance, Health, MaxHealth, MoveSpeed,
-    },
-    hostile,
-    projectile::ProjectileSpeed,
+    ability::{Ability, Slots},
+    attribute::Health,
+    balance::Balance,
+    modifier::{Modifier, Modifiers, Op, Source, Timed},
+    player::Player,
+    run::RunRng,
+    upgrade::Upgrades,
+    GameState,
 };
 
 pub struct Plugin;
@@ -17,16 +21,22 @@ impl prelude::Plugin for Plugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<Choices>()
             .add_event::<Apply>()
+            .add_event::<Select>()
+            .add_system(offer.in_set(OnUpdate(GameState::LevelUp)))
+            // Picks made anywhere during the frame are settled before the next one
+            .add_system(select.in_base_set(CoreSet::PostUpdate))
             .add_system(apply);
     }
 }
 
-#[derive(EnumIter, Clone, Copy, Debug, Display, PartialEq, Eq)]
+#[derive(
+    EnumIter, Clone, Copy, Debug, Display, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
 pub enum Affect {
     #[strum(serialize = "Max Health")]
     MaxHealth,
     #[strum(serialize = "Health Drop Chance")]
-    DropHealh,
+    DropHealth,
     Damage,
     #[strum(serialize = "Movement Speed")]
     MoveSpeed,
@@ -37,53 +47,116 @@ pub enum Affect {
     #[strum(serialize = "Projectile Duplication Chance")]
     DupChance,
     #[strum(serialize = "Projectile Speed")]
-    ProjectleSpeed,
+    ProjectileSpeed,
+    #[strum(serialize = "Health Regeneration")]
+    HealthRegen,
+    Lifesteal,
+    Armor,
+    #[strum(serialize = "Critical Hit Chance")]
+    CritChance,
+    #[strum(serialize = "Critical Hit Damage")]
+    CritMultiplier,
+    Knockback,
+    #[strum(serialize = "Pickup Radius")]
+    PickupRadius,
+    #[strum(serialize = "Invulnerability Time")]
+    Invulnerability,
+    #[strum(serialize = "Dash Distance")]
+    DashDistance,
+    #[strum(serialize = "Dash Cooldown")]
+    DashCooldown,
 }
 
-#[derive(Resource, Default)]
+impl Affect {
+    /// Attributes measuring a wait, which upgrades shorten
+    pub fn is_duration(self) -> bool {
+        matches!(self, Affect::AttackSpeed | Affect::DashCooldown)
+    }
+
+    /// Attributes the player starts without, which a debuff has nothing to take from
+    pub fn starts_at_zero(self) -> bool {
+        matches!(
+            self,
+            Affect::HealthRegen | Affect::Lifesteal | Affect::Armor
+        )
+    }
+
+    /// How a diff of `value` modifies this attribute, see [`Balance::flat_upgrades`]
+    pub fn op(self, value: f32, balance: &Balance) -> Op {
+        match balance.flat_upgrades.get(&self) {
+            Some(scale) => Op::Add(value * scale),
+            None => Op::Mul(value),
+        }
+    }
+}
+
+#[derive(Resource, Default, Clone, Debug, Serialize, Deserialize)]
 pub struct Choices {
     pub inner: Vec<(Diff, Diff)>,
     pub remaining: u32,
+    /// Pairs picked so far this run
+    pub picked: u32,
+    /// Ability that can be learned instead of picking a pair
+    pub ability: Option<Ability>,
 }
 
 impl Choices {
-    pub fn random(count: u32, rng: &mut GlobalRng) -> Self {
+    /// `count` pairs rolled from `upgrades`, plus with `ability_chance` an ability the
+    /// player doesn't hold
+    pub fn random(
+        count: u32,
+        ability_chance: f64,
+        upgrades: &Upgrades,
+        held: &[Ability],
+        rng: &mut GlobalRng,
+    ) -> Self {
+        let candidates: Vec<_> = Ability::iter()
+            .filter(|ability| !held.contains(ability))
+            .collect();
         Self {
-            inner: (0..count)
-                .map(|_| {
-                    let buff = Diff::random(rng, None);
-                    let debuff = Diff::random(rng, Some(buff.affect)).neg();
-                    (buff, debuff)
-                })
-                .collect(),
+            inner: (0..count).filter_map(|_| upgrades.roll(rng)).collect(),
             remaining: 0,
+            picked: 0,
+            ability: (!candidates.is_empty() && rng.chance(ability_chance))
+                .then(|| rng.sample(&candidates).copied())
+                .flatten(),
         }
     }
 
-    pub fn randomize(&mut self, count: u32, rng: &mut GlobalRng) {
-        let remaining = self.remaining;
-        *self = Self::random(count, rng);
-        self.remaining = remaining;
+    pub fn randomize(
+        &mut self,
+        count: u32,
+        ability_chance: f64,
+        upgrades: &Upgrades,
+        held: &[Ability],
+        rng: &mut GlobalRng,
+    ) {
+        let random = Self::random(count, ability_chance, upgrades, held, rng);
+        self.inner = random.inner;
+        self.ability = random.ability;
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Diff {
     pub affect: Affect,
     pub value: f32,
 }
 
 impl Diff {
-    pub fn random(rng: &mut GlobalRng, skip: Option<Affect>) -> Self {
-        let affect = rng
-            .sample_iter(Affect::iter().filter(|a| Some(*a) != skip))
-            .expect("Failed to sample affect");
-        let values = [0.05, 0.1, 0.15, 0.2, 0.25, 0.3, 0.35, 0.4, 0.45, 0.5];
-        let value = *rng.sample(&values).expect("Failed to sample value");
-        Self { affect, value }
+    pub fn modifier(self, source: Source, balance: &Balance) -> Modifier {
+        Modifier {
+            source,
+            affect: self.affect,
+            op: self.affect.op(self.value, balance),
+        }
     }
+}
+
+impl Neg for Diff {
+    type Output = Self;
 
-    pub fn neg(self) -> Self {
+    fn neg(self) -> Self {
         Self {
             affect: self.affect,
             value: -self.value,
@@ -93,55 +166,115 @@ impl Diff {
 
 pub struct Apply {
     pub diff: Diff,
+    pub source: Source,
     pub target: Entity,
+    /// Taken back after this long, permanent when `None`
+    pub duration: Option<Duration>,
+}
+
+/// Pick the buff/debuff pair at this index of [`Choices`] for the player
+pub struct Select(pub usize);
+
+fn offer(
+    player: Query<&Slots, With<Player>>,
+    mut choices: ResMut<Choices>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut rng: ResMut<RunRng>,
+    balance: Res<Balance>,
+    upgrades: Res<Upgrades>,
+) {
+    if choices.remaining == 0 {
+        game_state.set(GameState::Game);
+        return;
+    }
+
+    if choices.inner.is_empty() {
+        let held: Vec<_> = player.iter().flat_map(Slots::abilities).collect();
+        choices.randomize(
+            3,
+            balance.ability_chance,
+            &upgrades,
+            &held,
+            &mut rng.choices,
+        );
+    }
+
+    // Upgrades that can't be paired leave nothing to pick
+    if choices.inner.is_empty() {
+        warn!("No upgrades to offer, skipping the level up");
+        choices.remaining = 0;
+        game_state.set(GameState::Game);
+    }
+}
+
+fn select(
+    mut reader: EventReader<Select>,
+    player: Query<(Entity, &Slots), With<Player>>,
+    mut choices: ResMut<Choices>,
+    mut writer: EventWriter<Apply>,
+    mut rng: ResMut<RunRng>,
+    balance: Res<Balance>,
+    upgrades: Res<Upgrades>,
+) {
+    for &Select(index) in reader.iter() {
+        let (Some(&(positive, negative)), Ok((player, slots))) =
+            (choices.inner.get(index), player.get_single())
+        else {
+            continue;
+        };
+        if choices.remaining == 0 {
+            continue;
+        }
+
+        let source = Source::Upgrade(choices.picked);
+        writer.send_batch([
+            Apply {
+                diff: positive,
+                source,
+                target: player,
+                duration: None,
+            },
+            Apply {
+                diff: negative,
+                source,
+                target: player,
+                duration: None,
+            },
+        ]);
+        choices.remaining -= 1;
+        choices.picked += 1;
+        let held: Vec<_> = slots.abilities().collect();
+        choices.randomize(
+            3,
+            balance.ability_chance,
+            &upgrades,
+            &held,
+            &mut rng.choices,
+        );
+    }
 }
 
 fn apply(
     mut reader: EventReader<Apply>,
-    mut query: Query<(
-        &mut MaxHealth,
-        &mut Health,
-        &mut HealChance,
-        &mut Damage,
-        &mut MoveSpeed,
-        &mut AttackSpeed,
-        &mut AttackRange,
-        &mut DupChance,
-        &mut ProjectileSpeed,
-    )>,
-    _spawn_rate: ResMut<hostile::SpawnRate>,
+    mut query: Query<(&mut Modifiers, &mut Timed, &mut Health)>,
+    balance: Res<Balance>,
 ) {
     for event in reader.iter() {
-        if let Ok((
-            mut max_health,
-            mut health,
-            mut heal_chance,
-            mut damage,
-            mut move_speed,
-            mut attack_speed,
-            mut attack_range,
-            mut dup_chance,
-            mut projectile_speed,
-        )) = query.get_mut(event.target)
-        {
-            let percent = 1. + event.diff.value;
-            match event.diff.affect {
-                Affect::MaxHealth => {
-                    max_health.0 = (((max_health.0 as f32) * percent) as i32).max(1);
-                    health.0 = (((health.0 as f32) * percent) as i32).max(1);
-                }
-                Affect::Damage => damage.0 = (((damage.0 as f32) * percent) as i32).max(1),
-                Affect::MoveSpeed => move_speed.0 *= percent,
-                Affect::AttackSpeed => {
-                    attack_speed.0 = Duration::from_secs_f32(
-                        attack_speed.0.as_secs_f32() * (1. - percent.sub(1.)),
-                    )
-                }
-                Affect::AttackRange => attack_range.0 = (attack_range.0 * percent).max(10.),
-                Affect::DupChance => dup_chance.0 *= percent,
-                Affect::ProjectleSpeed => projectile_speed.0 *= percent,
-                Affect::DropHealh => heal_chance.0 *= percent,
-            }
+        let Ok((mut modifiers, mut timed, mut health)) = query.get_mut(event.target) else {
+            continue;
+        };
+
+        let max_health = modifiers.value(Affect::MaxHealth);
+        let modifier = event.diff.modifier(event.source, &balance);
+        modifiers.add(modifier);
+        if let Some(duration) = event.duration {
+            timed.0.push((modifier, duration));
+        }
+
+        // Health grows and shrinks along with max health
+        if event.diff.affect == Affect::MaxHealth && max_health > 0. {
+            let ratio = modifiers.value(Affect::MaxHealth) / max_health;
+            health.0 = ((health.0 as f32 * ratio) as i32).max(1);
         }
     }
 }
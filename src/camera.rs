@@ -20,7 +20,10 @@ fn follow_player(
     camera: Query<Entity, With<Camera>>,
     time: Res<Time>,
 ) {
-    let player_transform = *transforms.get(player.single()).unwrap();
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    let player_transform = *transforms.get(player).unwrap();
     let mut camera_transform = transforms.get_mut(camera.single()).unwrap();
 
     camera_transform.translation = camera_transform
@@ -0,0 +1,158 @@
+use std::{
+    io,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::{self, *};
+use serde::{Deserialize, Serialize};
+
+use crate::{hostile::Score, run::Seed, save, stats::RunStats, GameState};
+
+/// Number of runs kept in the table
+pub const MAX_ENTRIES: usize = 10;
+
+pub struct Plugin;
+impl prelude::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HighScores::load())
+            .add_system(record.in_schedule(OnEnter(GameState::Dead)));
+    }
+}
+
+/// Best runs so far, highest score first
+#[derive(Debug, Default, Clone, Resource, Serialize, Deserialize)]
+pub struct HighScores {
+    pub entries: Vec<Entry>,
+    /// Index of the run that just ended, if it placed
+    #[serde(skip)]
+    pub latest: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub score: u128,
+    pub survived: Duration,
+    pub level: u32,
+    pub seed: u64,
+    /// Day the run ended, as `YYYY-MM-DD`
+    pub date: String,
+}
+
+impl HighScores {
+    pub fn path() -> PathBuf {
+        save::data_dir().join("highscores.ron")
+    }
+
+    /// Read the table from [`HighScores::path`], starting empty when there is none
+    pub fn load() -> Self {
+        match save::read_ron(&Self::path()) {
+            Ok(scores) => scores,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => default(),
+            Err(err) => {
+                warn!("Failed to load high scores: {err}");
+                default()
+            }
+        }
+    }
+
+    /// Place `entry` in the table, returning its index if it made the cut
+    pub fn insert(&mut self, entry: Entry) -> Option<usize> {
+        // Ties go to the run that got there first
+        let index = self
+            .entries
+            .iter()
+            .position(|other| other.score < entry.score)
+            .unwrap_or(self.entries.len());
+
+        self.latest = (index < MAX_ENTRIES).then(|| {
+            self.entries.insert(index, entry);
+            self.entries.truncate(MAX_ENTRIES);
+            index
+        });
+        self.latest
+    }
+}
+
+fn record(
+    mut scores: ResMut<HighScores>,
+    score: Res<Score>,
+    stats: Res<RunStats>,
+    seed: Res<Seed>,
+) {
+    let entry = Entry {
+        score: score.0,
+        survived: stats.survived,
+        level: stats.level(),
+        seed: seed.0,
+        date: today(),
+    };
+
+    if scores.insert(entry).is_none() {
+        return;
+    }
+
+    if let Err(err) = save::write_ron(&HighScores::path(), &*scores) {
+        error!("Failed to save high scores: {err}");
+    }
+}
+
+/// Current UTC date as `YYYY-MM-DD`
+fn today() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Calendar date of a day count since 1970-01-01, see
+/// <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(score: u128) -> Entry {
+        Entry {
+            score,
+            survived: Duration::ZERO,
+            level: 1,
+            seed: 0,
+            date: "1970-01-01".into(),
+        }
+    }
+
+    #[test]
+    fn keeps_the_best_runs_in_order() {
+        let mut scores = HighScores::default();
+        for score in 0..MAX_ENTRIES as u128 {
+            assert_eq!(scores.insert(entry(score * 10)), Some(0));
+        }
+
+        assert_eq!(scores.insert(entry(0)), None);
+        assert_eq!(scores.insert(entry(45)), Some(5));
+        assert_eq!(scores.entries.len(), MAX_ENTRIES);
+        assert_eq!(scores.entries.last(), Some(&entry(10)));
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(20_744), (2026, 10, 18));
+    }
+}
@@ -2,12 +2,17 @@ use std::time::Duration;
 
 use bevy::prelude::{self, *};
 use bevy_rapier2d::prelude::*;
-use bevy_turborand::{DelegatedRng, GlobalRng};
+use bevy_turborand::DelegatedRng;
 
 use crate::{
-    attribute::{self, AttackSpeedTimer, Damage, Health, MaxHealth},
+    ability::TimeSlow,
+    attribute::{
+        self, Armor, AttackSpeedTimer, Damage, DamageEvent, Health, Invulnerability, MaxHealth,
+    },
+    balance::{self, Balance},
     collision, loot,
-    player::Player,
+    player::{Invulnerable, Player},
+    run::RunRng,
     ui::ORANGE,
     GameState,
 };
@@ -15,9 +20,10 @@ use crate::{
 pub struct Plugin;
 impl prelude::Plugin for Plugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(SpawnRate(Duration::from_millis(500)))
-            .insert_resource(SpawnTimer(Timer::from_seconds(2.0, TimerMode::Repeating)))
+        app.init_resource::<SpawnRate>()
+            .init_resource::<SpawnTimer>()
             .init_resource::<Score>()
+            .add_event::<EnemyDeathEvent>()
             .add_system(despawn_all_hostiles.in_set(OnUpdate(GameState::Dead)))
             .add_systems(
                 (
@@ -26,6 +32,7 @@ impl prelude::Plugin for Plugin {
                     update_spawn_timer,
                     move_to_player,
                     attack_player,
+                    recover,
                 )
                     .in_set(OnUpdate(GameState::Game)),
             );
@@ -34,6 +41,9 @@ impl prelude::Plugin for Plugin {
 
 #[derive(Default, Component)]
 pub struct Hostile;
+/// Knocked back, the hostile stops chasing the player until the timer finishes
+#[derive(Debug, Clone, Component)]
+pub struct HitStun(pub Timer);
 #[derive(Debug, Clone, Resource)]
 pub struct SpawnTimer(pub Timer);
 #[derive(Debug, Clone, Resource)]
@@ -41,6 +51,18 @@ pub struct SpawnRate(pub Duration);
 #[derive(Debug, Default, Clone, Resource)]
 pub struct Score(pub u128);
 
+impl Default for SpawnTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(2.0, TimerMode::Repeating))
+    }
+}
+
+impl Default for SpawnRate {
+    fn default() -> Self {
+        Self(Duration::from_millis(500))
+    }
+}
+
 fn update_spawn_timer(mut timer: ResMut<SpawnTimer>, rate: Res<SpawnRate>) {
     if !rate.is_changed() {
         return;
@@ -53,56 +75,80 @@ fn spawn(
     mut commands: Commands,
     time: Res<Time>,
     mut timer: ResMut<SpawnTimer>,
-    mut rng: ResMut<GlobalRng>,
+    mut rng: ResMut<RunRng>,
     player: Query<&Transform, With<Player>>,
+    balance: Res<Balance>,
 ) {
     let player = player.single();
     timer.0.tick(time.delta());
     if timer.0.just_finished() {
         let pos = {
-            let x = rng.f32_normalized();
-            let y = rng.f32_normalized();
+            let x = rng.spawn.f32_normalized();
+            let y = rng.spawn.f32_normalized();
             let val = Vec3 { x, y, z: 0. }.normalize();
-            player.translation + val * 1000.
+            player.translation + val * balance.spawn_distance
         };
 
-        let transform = Transform::from_translation(pos);
-        let mut commands = commands.spawn(SpriteBundle {
-            sprite: Sprite {
-                color: ORANGE.into(),
-                custom_size: Some(Vec2::new(30.0, 30.0)),
-                ..default()
-            },
-            transform,
-            ..default()
-        });
-
-        commands.insert((
-            Hostile,
-            GravityScale(0.0),
-            Velocity::default(),
-            RigidBody::Dynamic,
-            Collider::cuboid(15., 15.),
-            Friction::coefficient(0.),
-            CollisionGroups::new(
-                collision::group::HOSTILE,
-                collision::group::PLAYER_PROJECTILE
-                    | collision::group::HOSTILE
-                    | collision::group::PLAYER,
-            ),
-            ActiveEvents::COLLISION_EVENTS,
-        ));
-
-        attribute::insert_common(&mut commands);
+        spawn_at(&mut commands, pos, &balance.hostile);
     }
 }
 
+/// Spawn a hostile with fresh stats at `translation`
+pub fn spawn_at(commands: &mut Commands, translation: Vec3, stats: &balance::Stats) -> Entity {
+    let transform = Transform::from_translation(translation);
+    let mut commands = commands.spawn(SpriteBundle {
+        sprite: Sprite {
+            color: ORANGE.into(),
+            custom_size: Some(Vec2::new(30.0, 30.0)),
+            ..default()
+        },
+        transform,
+        ..default()
+    });
+
+    commands.insert((
+        Hostile,
+        GravityScale(0.0),
+        Velocity::default(),
+        RigidBody::Dynamic,
+        Collider::cuboid(15., 15.),
+        Friction::coefficient(0.),
+        CollisionGroups::new(
+            collision::group::HOSTILE,
+            collision::group::PLAYER_PROJECTILE
+                | collision::group::HOSTILE
+                | collision::group::PLAYER,
+        ),
+        ActiveEvents::COLLISION_EVENTS,
+    ));
+
+    attribute::insert_common(&mut commands, stats);
+    commands.id()
+}
+
 fn attack_player(
     context: Res<RapierContext>,
     mut hostiles: Query<(Entity, &Damage, &mut AttackSpeedTimer), With<Hostile>>,
-    mut player: Query<(Entity, &mut Health), With<Player>>,
+    mut player: Query<
+        (
+            Entity,
+            &mut Health,
+            &Armor,
+            &Invulnerability,
+            Option<&Invulnerable>,
+        ),
+        With<Player>,
+    >,
+    mut commands: Commands,
+    mut damage_writer: EventWriter<DamageEvent>,
+    balance: Res<Balance>,
 ) {
-    let (player, mut health) = player.single_mut();
+    let (player, mut health, armor, invulnerability, invulnerable) = player.single_mut();
+    // Hostiles keep their attack ready until the player can be hit again
+    if invulnerable.is_some() {
+        return;
+    }
+
     for (hostile, damage, mut timer) in hostiles.iter_mut() {
         let has_contact = context
             .contact_pair(hostile, player)
@@ -113,9 +159,23 @@ fn attack_player(
             continue;
         }
 
-        health.0 = health.0.saturating_sub(damage.0);
+        let amount = attribute::mitigate(damage.0, armor, &balance);
+        health.0 = health.0.saturating_sub(amount);
+        damage_writer.send(DamageEvent {
+            source: hostile,
+            target: player,
+            amount,
+            crit: false,
+        });
 
         timer.0.reset();
+
+        if !invulnerability.0.is_zero() {
+            commands
+                .entity(player)
+                .insert(Invulnerable(Timer::new(invulnerability.0, TimerMode::Once)));
+            break;
+        }
     }
 }
 
@@ -125,11 +185,17 @@ fn despawn_all_hostiles(mut commands: Commands, query: Query<Entity, With<Hostil
     }
 }
 
-fn move_to_player(
-    mut query: Query<(&Transform, &mut Velocity), With<Hostile>>,
+pub fn move_to_player(
+    mut query: Query<(&Transform, &mut Velocity), (With<Hostile>, Without<HitStun>)>,
     player: Query<&Transform, With<Player>>,
+    slow: Option<Res<TimeSlow>>,
+    balance: Res<Balance>,
 ) {
     let player_transform = player.single();
+    let speed = match slow {
+        Some(_) => balance.hostile_speed * balance.time_slow_factor,
+        None => balance.hostile_speed,
+    };
 
     for (transform, mut velocity) in query.iter_mut() {
         let direction = {
@@ -138,7 +204,16 @@ fn move_to_player(
         };
         let direction = direction.normalize_or_zero();
 
-        velocity.linvel = direction * 100.;
+        velocity.linvel = direction * speed;
+    }
+}
+
+fn recover(mut commands: Commands, mut query: Query<(Entity, &mut HitStun)>, time: Res<Time>) {
+    for (entity, mut stun) in query.iter_mut() {
+        stun.0.tick(time.delta());
+        if stun.0.finished() {
+            commands.entity(entity).remove::<HitStun>();
+        }
     }
 }
 
@@ -0,0 +1,103 @@
+pub mod ability;
+pub mod attribute;
+pub mod audio;
+pub mod balance;
+pub mod buff;
+pub mod camera;
+pub mod collision;
+pub mod highscore;
+pub mod hostile;
+pub mod loot;
+pub mod modifier;
+pub mod music;
+pub mod player;
+pub mod projectile;
+pub mod replay;
+pub mod ron_asset;
+pub mod run;
+pub mod save;
+pub mod stats;
+pub mod ui;
+pub mod upgrade;
+
+use std::time::Duration;
+
+use bevy::{
+    app::PluginGroupBuilder,
+    input::InputPlugin,
+    prelude::*,
+    time::{TimeSystem, TimeUpdateStrategy},
+};
+use bevy_rapier2d::prelude::*;
+use bevy_turborand::RngPlugin;
+
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
+pub enum GameState {
+    #[default]
+    MainMenu,
+    Game,
+    Paused,
+    LevelUp,
+    Dead,
+}
+
+/// Every plugin making up the game. Expects [`bevy_rapier2d`], [`bevy_turborand`],
+/// [`bevy_egui`] and [`bevy_kira_audio`] plugins to be added alongside.
+pub struct GamePlugins;
+impl PluginGroup for GamePlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(run::Plugin)
+            .add(balance::Plugin)
+            .add(upgrade::Plugin)
+            .add(camera::Plugin)
+            .add(loot::Plugin)
+            .add(player::Plugin)
+            .add(projectile::Plugin)
+            .add(hostile::Plugin)
+            .add(buff::Plugin)
+            .add(modifier::Plugin)
+            .add(save::Plugin)
+            .add(stats::Plugin)
+            .add(highscore::Plugin)
+            .add(attribute::Plugin)
+            .add(ability::Plugin)
+            .add(ui::Plugin)
+            .add(replay::Plugin)
+            .add(music::Plugin)
+            .add(audio::Plugin)
+    }
+}
+
+/// Build the game on [`MinimalPlugins`] without a window, rendering or audio, starting
+/// straight into a run. Every update advances a fixed 60 FPS frame so simulations don't
+/// depend on the host machine. The `camera`, `ui`, `music` and `audio` plugins can be
+/// added back on top when needed, `highscore` is left out so simulated runs never reach
+/// the player's table.
+pub fn headless_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(TransformPlugin)
+        .add_plugin(InputPlugin)
+        .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
+        .add_plugin(RngPlugin::default())
+        .add_plugins(
+            GamePlugins
+                .build()
+                .disable::<camera::Plugin>()
+                .disable::<highscore::Plugin>()
+                .disable::<ui::Plugin>()
+                .disable::<music::Plugin>()
+                .disable::<audio::Plugin>(),
+        )
+        .add_system(step_clock.in_base_set(CoreSet::First).before(TimeSystem))
+        .world
+        .send_event(run::RestartEvent);
+    app
+}
+
+/// Advance [`Time`] by exactly one frame, regardless of how long the update took
+fn step_clock(time: Res<Time>, mut strategy: ResMut<TimeUpdateStrategy>) {
+    let last = time.last_update().unwrap_or_else(|| time.startup());
+    *strategy = TimeUpdateStrategy::ManualInstant(last + Duration::from_secs_f64(1. / 60.));
+}
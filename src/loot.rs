@@ -1,3 +1,5 @@
+use std::{ops::Range, time::Duration};
+
 use bevy::{
     prelude::{self, *},
     sprite::MaterialMesh2dBundle,
@@ -6,19 +8,24 @@ use bevy_rapier2d::prelude::*;
 use bevy_turborand::{DelegatedRng, GlobalRng};
 
 use crate::{
-    attribute::{Experience, HealChance, Health, MoveSpeed},
+    attribute::{Experience, HealChance, Health, MoveSpeed, PickupRadius},
+    balance::Balance,
+    buff::{self, Affect, Diff},
     collision,
+    modifier::Source,
     player::Player,
-    ui::{RED, YELLOW},
+    run::RunRng,
+    ui::{GREEN, RED, YELLOW},
+    GameState,
 };
 
 pub struct Plugin;
 impl prelude::Plugin for Plugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<Event>()
+        app.init_resource::<Handles>()
+            .add_event::<Event>()
             .add_system(drop_loot)
-            .add_system(pickup_loot)
-            .add_system(move_loot_to_player);
+            .add_systems((pickup_loot, move_loot_to_player).in_set(OnUpdate(GameState::Game)));
     }
 }
 
@@ -28,13 +35,46 @@ pub struct Event(pub Vec3);
 pub enum Loot {
     Health,
     Experience,
+    /// Speeds the player up for a while
+    Haste,
+}
+
+/// Render handles shared by every loot drop, left empty when running headless.
+#[derive(Resource)]
+struct Handles {
+    mesh: Handle<Mesh>,
+    health: Handle<ColorMaterial>,
+    experience: Handle<ColorMaterial>,
+    haste: Handle<ColorMaterial>,
+}
+
+impl FromWorld for Handles {
+    fn from_world(world: &mut World) -> Self {
+        let mut handles = Self {
+            mesh: default(),
+            health: default(),
+            experience: default(),
+            haste: default(),
+        };
+        if let Some(mut meshes) = world.get_resource_mut::<Assets<Mesh>>() {
+            handles.mesh = meshes.add(shape::Cube::new(5.).into());
+        }
+        if let Some(mut materials) = world.get_resource_mut::<Assets<ColorMaterial>>() {
+            handles.health = materials.add(ColorMaterial::from(Color::from(RED)));
+            handles.experience = materials.add(ColorMaterial::from(Color::from(YELLOW)));
+            handles.haste = materials.add(ColorMaterial::from(Color::from(GREEN)));
+        }
+        handles
+    }
 }
 
-fn pickup_loot(
+pub fn pickup_loot(
     context: Res<RapierContext>,
     loot: Query<(Entity, &Loot)>,
     mut player: Query<(Entity, &mut Experience, &mut Health), With<Player>>,
     mut commands: Commands,
+    mut apply_writer: EventWriter<buff::Apply>,
+    balance: Res<Balance>,
 ) {
     let (player, mut xp, mut health) = player.single_mut();
     for (entity, loot) in loot.iter() {
@@ -47,6 +87,15 @@ fn pickup_loot(
                     Loot::Experience => {
                         xp.current += 1;
                     }
+                    Loot::Haste => apply_writer.send(buff::Apply {
+                        diff: Diff {
+                            affect: Affect::MoveSpeed,
+                            value: balance.haste_boost,
+                        },
+                        source: Source::Pickup,
+                        target: player,
+                        duration: Some(Duration::from_secs_f32(balance.haste_duration)),
+                    }),
                 }
                 commands.entity(entity).despawn();
             }
@@ -54,20 +103,22 @@ fn pickup_loot(
     }
 }
 
+/// Pull loot within the player's [`PickupRadius`] in, the faster the player moves
 fn move_loot_to_player(
     mut query: Query<(&mut Velocity, &Transform), With<Loot>>,
-    player: Query<(&Transform, &MoveSpeed), With<Player>>,
+    player: Query<(&Transform, &MoveSpeed, &PickupRadius), With<Player>>,
     time: Res<Time>,
+    balance: Res<Balance>,
 ) {
-    let (player, _speed) = player.single();
+    let (player, speed, radius) = player.single();
     for (mut velocity, transform) in query.iter_mut() {
-        let direction = {
-            let val = (player.translation - transform.translation).normalize();
-            Vec2::new(val.x, val.y)
-        };
-        velocity.linvel = velocity
-            .linvel
-            .lerp(direction * 5. * 200., time.delta_seconds() * 3.);
+        let offset = (player.translation - transform.translation).truncate();
+        if offset.length() > radius.0 {
+            continue;
+        }
+
+        let target = offset.normalize_or_zero() * speed.0 * balance.loot_magnet;
+        velocity.linvel = velocity.linvel.lerp(target, time.delta_seconds() * 3.);
     }
 }
 
@@ -75,54 +126,50 @@ fn drop_loot(
     player: Query<&HealChance, With<Player>>,
     mut events: EventReader<Event>,
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut rng: ResMut<GlobalRng>,
+    handles: Res<Handles>,
+    mut rng: ResMut<RunRng>,
+    balance: Res<Balance>,
 ) {
-    let player = player.single();
+    let rng = &mut rng.loot;
+    let Ok(player) = player.get_single() else {
+        return;
+    };
     for &Event(pos) in events.iter() {
-        for _ in 0..rng.u32(10..30) {
-            spawn(
-                Loot::Experience,
-                &mut commands,
-                &mut meshes,
-                &mut materials,
-                pos,
-                &mut rng,
-            );
+        for _ in 0..count(rng, &balance.experience_drop) {
+            spawn(Loot::Experience, &mut commands, &handles, pos, rng);
         }
 
         if rng.f32() < player.0 {
-            for _ in 0..rng.u32(1..20) {
-                spawn(
-                    Loot::Health,
-                    &mut commands,
-                    &mut meshes,
-                    &mut materials,
-                    pos,
-                    &mut rng,
-                );
+            for _ in 0..count(rng, &balance.health_drop) {
+                spawn(Loot::Health, &mut commands, &handles, pos, rng);
             }
         }
+
+        if rng.f32() < balance.haste_chance {
+            spawn(Loot::Haste, &mut commands, &handles, pos, rng);
+        }
     }
 }
 
-fn spawn(
-    kind: Loot,
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<ColorMaterial>>,
-    pos: Vec3,
-    rng: &mut ResMut<GlobalRng>,
-) {
-    let color: Color = match kind {
-        Loot::Health => RED.into(),
-        Loot::Experience => YELLOW.into(),
+/// Amount of loot in `range`, none when the range is empty
+fn count(rng: &mut GlobalRng, range: &Range<u32>) -> u32 {
+    if range.is_empty() {
+        0
+    } else {
+        rng.u32(range.clone())
+    }
+}
+
+fn spawn(kind: Loot, commands: &mut Commands, handles: &Handles, pos: Vec3, rng: &mut GlobalRng) {
+    let material = match kind {
+        Loot::Health => handles.health.clone(),
+        Loot::Experience => handles.experience.clone(),
+        Loot::Haste => handles.haste.clone(),
     };
     commands
         .spawn(MaterialMesh2dBundle {
-            mesh: meshes.add(shape::Cube::new(5.).into()).into(),
-            material: materials.add(ColorMaterial::from(color)),
+            mesh: handles.mesh.clone().into(),
+            material,
             ..Default::default()
         })
         .insert((
@@ -133,6 +180,11 @@ fn spawn(
             ActiveEvents::COLLISION_EVENTS,
             GravityScale(0.0),
             Dominance::group(-1),
+            // Scattered loot out of reach comes to rest where it landed
+            Damping {
+                linear_damping: 3.,
+                angular_damping: 0.,
+            },
             Velocity::linear(
                 Vec2::new(rng.i8(i8::MIN..i8::MAX) as _, rng.i8(i8::MIN..i8::MAX) as _).normalize()
                     * 1000.,
@@ -1,54 +1,49 @@
-mod attribute;
-mod audio;
-mod buff;
-mod camera;
-mod collision;
-mod hostile;
-mod loot;
-mod music;
-mod player;
-mod projectile;
-mod ui;
-
-use attribute::LevelUpEvent;
 use bevy::prelude::*;
 use bevy_egui::EguiPlugin;
+use bevy_jam_3::{replay, run::SeedConfig, GamePlugins};
 use bevy_kira_audio::prelude::*;
 use bevy_rapier2d::prelude::*;
 use bevy_turborand::RngPlugin;
-use hostile::EnemyDeathEvent;
-use projectile::ShootEvent;
 
-#[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
-enum GameState {
-    #[default]
-    Game,
-    LevelUp,
-    Dead,
+/// Value following `name` on the command line
+fn arg(name: &str) -> Option<String> {
+    std::env::args().skip_while(|arg| arg != name).nth(1)
 }
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
-        .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
-        // .add_plugin(RapierDebugRenderPlugin::default())
-        .add_plugin(RngPlugin::default())
-        .add_plugin(EguiPlugin)
-        .add_state::<GameState>()
-        .add_plugin(AudioPlugin)
-        .add_plugin(camera::Plugin)
-        .add_plugin(loot::Plugin)
-        .add_plugin(player::Plugin)
-        .add_plugin(projectile::Plugin)
-        .add_plugin(hostile::Plugin)
-        .add_plugin(buff::Plugin)
-        .add_plugin(attribute::Plugin)
-        .add_plugin(ui::Plugin)
-        .add_plugin(music::Plugin)
-        .add_plugin(audio::Plugin)
-        //.insert_resource(SpacialAudio { max_distance: 25. })
-        .add_event::<LevelUpEvent>()
-        .add_event::<ShootEvent>()
-        .add_event::<EnemyDeathEvent>()
-        .run();
+    let seed = arg("--seed").map(|seed| seed.parse().expect("--seed expects an unsigned integer"));
+
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins.set(AssetPlugin {
+        // Hot reload assets/game.balance.ron
+        watch_for_changes: true,
+        ..default()
+    }))
+    .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
+    // Rapier caps a step at 1/60 s by default, slowing every body down below 60 FPS.
+    // Longer steps keep speeds true down to 15 FPS.
+    .insert_resource(RapierConfiguration {
+        timestep_mode: TimestepMode::Variable {
+            max_dt: 1. / 15.,
+            time_scale: 1.,
+            substeps: 1,
+        },
+        ..default()
+    })
+    // .add_plugin(RapierDebugRenderPlugin::default())
+    .add_plugin(RngPlugin::default())
+    .add_plugin(EguiPlugin)
+    .add_plugin(AudioPlugin)
+    .add_plugins(GamePlugins)
+    //.insert_resource(SpacialAudio { max_distance: 25. })
+    .insert_resource(SeedConfig(seed));
+
+    if let Some(path) = arg("--record") {
+        replay::record(&mut app, path);
+    }
+    if let Some(path) = arg("--replay") {
+        replay::replay(&mut app, path).expect("Failed to load replay");
+    }
+
+    app.run();
 }
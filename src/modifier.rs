@@ -0,0 +1,251 @@
+use std::{collections::BTreeMap, fmt, time::Duration};
+
+use bevy::prelude::{self, *};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    attribute::{
+        Armor, AttackRange, AttackSpeed, CritChance, CritMultiplier, Damage, DashCooldown,
+        DashDistance, DupChance, HealChance, HealthRegen, Invulnerability, Knockback, Lifesteal,
+        MaxHealth, MoveSpeed, PickupRadius,
+    },
+    balance::Balance,
+    buff::Affect,
+    projectile::ProjectileSpeed,
+    GameState,
+};
+
+pub struct Plugin;
+impl prelude::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(expire.in_set(OnUpdate(GameState::Game)))
+            .add_system(recompute.in_base_set(CoreSet::PostUpdate));
+    }
+}
+
+/// Where a [`Modifier`] came from, so everything it added can be taken back together
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Source {
+    /// The nth buff/debuff pair picked at level up
+    Upgrade(u32),
+    /// Loot picked up off the ground
+    Pickup,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Op {
+    /// Added to the base value, in the attribute's own unit
+    Add(f32),
+    /// Scales the value by `1 + x`. Attack speed and dash cooldown are durations, so
+    /// they shrink by `1 - x` instead.
+    Mul(f32),
+}
+
+impl Op {
+    pub fn is_negative(self) -> bool {
+        match self {
+            Op::Add(value) | Op::Mul(value) => value < 0.,
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Op::Add(value) => write!(f, "{value:+.2}"),
+            Op::Mul(value) => write!(f, "{:+.2}%", value * 100.),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Modifier {
+    pub source: Source,
+    pub affect: Affect,
+    pub op: Op,
+}
+
+/// Base value of every attribute plus the modifiers stacked on top. The attribute
+/// components are recomputed from it whenever it changes.
+#[derive(Debug, Default, Clone, Component, Serialize, Deserialize)]
+pub struct Modifiers {
+    base: BTreeMap<Affect, f32>,
+    stack: Vec<Modifier>,
+}
+
+impl Modifiers {
+    pub fn new(base: impl IntoIterator<Item = (Affect, f32)>) -> Self {
+        Self {
+            base: base.into_iter().collect(),
+            stack: Vec::new(),
+        }
+    }
+
+    pub fn base(&self, affect: Affect) -> f32 {
+        self.base.get(&affect).copied().unwrap_or_default()
+    }
+
+    pub fn set_base(&mut self, affect: Affect, value: f32) {
+        self.base.insert(affect, value);
+    }
+
+    pub fn add(&mut self, modifier: Modifier) {
+        self.stack.push(modifier);
+    }
+
+    /// Take back every modifier added by `source`
+    pub fn remove(&mut self, source: Source) {
+        self.stack.retain(|modifier| modifier.source != source);
+    }
+
+    /// Take back a single modifier, leaving any identical ones in place
+    pub fn take(&mut self, modifier: &Modifier) {
+        if let Some(index) = self.stack.iter().position(|other| other == modifier) {
+            self.stack.remove(index);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Modifier> {
+        self.stack.iter()
+    }
+
+    /// Base plus every additive modifier, then scaled by every multiplicative one
+    pub fn value(&self, affect: Affect) -> f32 {
+        let (add, scale) = self
+            .stack
+            .iter()
+            .filter(|modifier| modifier.affect == affect)
+            .fold((0., 1.), |(add, scale), modifier| match modifier.op {
+                Op::Add(value) => (add + value, scale),
+                Op::Mul(value) if affect.is_duration() => (add, scale * (1. - value)),
+                Op::Mul(value) => (add, scale * (1. + value)),
+            });
+        (self.base(affect) + add) * scale
+    }
+}
+
+/// Modifiers taken back from the [`Modifiers`] stack once their time runs out, with
+/// the time they have left
+#[derive(Debug, Default, Clone, Component, Serialize, Deserialize)]
+pub struct Timed(pub Vec<(Modifier, Duration)>);
+
+fn expire(mut query: Query<(&mut Modifiers, &mut Timed)>, time: Res<Time>) {
+    for (mut modifiers, mut timed) in query.iter_mut() {
+        if timed.0.is_empty() {
+            continue;
+        }
+
+        for (_, remaining) in timed.0.iter_mut() {
+            *remaining = remaining.saturating_sub(time.delta());
+        }
+        for (modifier, _) in timed.0.iter().filter(|(_, remaining)| remaining.is_zero()) {
+            modifiers.take(modifier);
+        }
+        timed.0.retain(|(_, remaining)| !remaining.is_zero());
+    }
+}
+
+/// Write every attribute from its modifiers, within the limits of the [`Balance`]
+#[allow(clippy::type_complexity)]
+fn recompute(
+    mut query: Query<(
+        Ref<Modifiers>,
+        (
+            &mut Damage,
+            &mut AttackSpeed,
+            &mut AttackRange,
+            &mut ProjectileSpeed,
+            &mut DupChance,
+            &mut CritChance,
+            &mut CritMultiplier,
+            &mut Knockback,
+        ),
+        (&mut MaxHealth, &mut HealthRegen, &mut Lifesteal, &mut Armor),
+        (&mut MoveSpeed, &mut HealChance),
+        // Only the player has one
+        Option<(
+            &mut PickupRadius,
+            &mut Invulnerability,
+            &mut DashDistance,
+            &mut DashCooldown,
+        )>,
+    )>,
+    balance: Res<Balance>,
+) {
+    for (modifiers, offense, defense, utility, player) in query.iter_mut() {
+        // Freshly inserted stacks without modifiers already match the attributes inserted
+        // next to them, unlike resumed ones, and new limits only matter to attributes that
+        // were modified
+        let modified = modifiers.iter().next().is_some();
+        let fresh = modifiers.is_added() && !modified;
+        let relimit = balance.is_changed() && modified;
+        if fresh || !(modifiers.is_changed() || relimit) {
+            continue;
+        }
+
+        let value = |affect| balance.limit(affect).apply(modifiers.value(affect));
+
+        let (
+            mut damage,
+            mut attack_speed,
+            mut attack_range,
+            mut projectile_speed,
+            mut dup_chance,
+            mut crit_chance,
+            mut crit_multiplier,
+            mut knockback,
+        ) = offense;
+        damage.0 = value(Affect::Damage).round() as i32;
+        attack_speed.0 = Duration::from_secs_f32(value(Affect::AttackSpeed).max(0.));
+        attack_range.0 = value(Affect::AttackRange);
+        projectile_speed.0 = value(Affect::ProjectileSpeed);
+        dup_chance.0 = value(Affect::DupChance);
+        crit_chance.0 = value(Affect::CritChance);
+        crit_multiplier.0 = value(Affect::CritMultiplier);
+        knockback.0 = value(Affect::Knockback);
+
+        let (mut max_health, mut health_regen, mut lifesteal, mut armor) = defense;
+        max_health.0 = value(Affect::MaxHealth).round() as i32;
+        health_regen.0 = value(Affect::HealthRegen);
+        lifesteal.0 = value(Affect::Lifesteal);
+        armor.0 = value(Affect::Armor);
+
+        let (mut move_speed, mut heal_chance) = utility;
+        move_speed.0 = value(Affect::MoveSpeed);
+        heal_chance.0 = value(Affect::DropHealth);
+
+        let Some((mut pickup_radius, mut invulnerability, mut dash_distance, mut dash_cooldown)) =
+            player
+        else {
+            continue;
+        };
+        pickup_radius.0 = value(Affect::PickupRadius);
+        invulnerability.0 = Duration::from_secs_f32(value(Affect::Invulnerability).max(0.));
+        dash_distance.0 = value(Affect::DashDistance);
+        dash_cooldown.0 = Duration::from_secs_f32(value(Affect::DashCooldown).max(0.));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stack_is_reversible() {
+        let mut modifiers = Modifiers::new([(Affect::Damage, 35.), (Affect::AttackSpeed, 0.5)]);
+        for (source, affect, op) in [
+            (Source::Upgrade(0), Affect::Damage, Op::Mul(0.2)),
+            (Source::Upgrade(0), Affect::AttackSpeed, Op::Mul(0.5)),
+            (Source::Upgrade(1), Affect::Damage, Op::Add(5.)),
+        ] {
+            modifiers.add(Modifier { source, affect, op });
+        }
+
+        assert_eq!(modifiers.value(Affect::Damage), 48.);
+        assert_eq!(modifiers.value(Affect::AttackSpeed), 0.25);
+
+        modifiers.remove(Source::Upgrade(0));
+        assert_eq!(modifiers.value(Affect::Damage), 40.);
+        assert_eq!(modifiers.value(Affect::AttackSpeed), 0.5);
+    }
+}
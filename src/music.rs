@@ -1,19 +1,41 @@
-use bevy_kira_audio::{AudioControl, AudioChannel};
-use bevy::prelude::{*, self};
+use bevy::prelude::{self, *};
+use bevy_kira_audio::{AudioChannel, AudioControl};
 
-use crate::audio::BgmChannel;
+use crate::{
+    audio::{BgmChannel, Volume},
+    GameState,
+};
 
 pub struct Plugin;
 impl prelude::Plugin for Plugin {
     fn build(&self, app: &mut App) {
-        app.add_system(start_bgm.on_startup());
+        app.add_system(start_bgm.in_schedule(OnEnter(GameState::Game)))
+            .add_system(update_volume);
     }
 }
 
-fn start_bgm(asset_server: Res<AssetServer>, bgm_audio: Res<AudioChannel<BgmChannel>>) {
+const BGM_VOLUME: f64 = 0.01;
+
+fn start_bgm(
+    asset_server: Res<AssetServer>,
+    bgm_audio: Res<AudioChannel<BgmChannel>>,
+    volume: Res<Volume>,
+    mut started: Local<bool>,
+) {
+    if *started {
+        return;
+    }
+    *started = true;
+
     let bgm_track = asset_server.load("music_fun_funky_whistle_groove_loop.wav");
-    let _bgm = bgm_audio
-        .play(bgm_track).looped()
-        .handle();
-    bgm_audio.set_volume(0.01);
-}
\ No newline at end of file
+    let _bgm = bgm_audio.play(bgm_track).looped().handle();
+    bgm_audio.set_volume(BGM_VOLUME * volume.0);
+}
+
+fn update_volume(bgm_audio: Res<AudioChannel<BgmChannel>>, volume: Res<Volume>) {
+    if !volume.is_changed() {
+        return;
+    }
+
+    bgm_audio.set_volume(BGM_VOLUME * volume.0);
+}
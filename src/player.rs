@@ -1,8 +1,12 @@
+use std::time::Duration;
+
 use bevy::prelude::{self, *};
 use bevy_rapier2d::prelude::*;
 
 use crate::{
-    attribute::{self, Experience, Health, MoveSpeed},
+    ability::{self, Ability, Activate, Slots},
+    attribute::{self, DashDistance, Experience, Health, Level, MoveSpeed},
+    balance::Balance,
     collision,
     ui::BLUE,
     GameState,
@@ -11,15 +15,57 @@ use crate::{
 pub struct Plugin;
 impl prelude::Plugin for Plugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(spawn)
-            .add_systems((move_player, die).in_set(OnUpdate(GameState::Game)));
+        app.init_resource::<Controls>()
+            .add_system(spawn.in_schedule(OnEnter(GameState::Game)))
+            .add_system(read_keyboard.in_set(ControlSet))
+            .add_systems(
+                (
+                    dash.after(ability::activate),
+                    move_player.after(dash),
+                    die,
+                    blink,
+                )
+                    .in_set(OnUpdate(GameState::Game)),
+            );
     }
 }
 
 #[derive(Default, Component)]
 pub struct Player;
 
-pub fn spawn(mut commands: Commands) {
+/// Ignoring hits until the timer finishes, see [`attribute::Invulnerability`]
+#[derive(Component)]
+pub struct Invulnerable(pub Timer);
+
+/// Seconds the sprite of an [`Invulnerable`] player spends dimmed or lit
+const BLINK: f32 = 0.1;
+
+/// Movement and shooting directions wanted this frame
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct Controls {
+    pub movement: Vec2,
+    pub shoot: Vec2,
+    /// Slot of [`Slots`] whose ability is triggered
+    pub ability: Option<usize>,
+}
+
+/// Carried along at a fixed velocity until the timer finishes, ignoring movement input
+#[derive(Component)]
+pub struct Dashing {
+    pub timer: Timer,
+    pub velocity: Vec2,
+}
+
+/// Systems filling [`Controls`], anything reading them runs after
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ControlSet;
+
+fn spawn(mut commands: Commands, existing: Query<(), With<Player>>, balance: Res<Balance>) {
+    // Resuming from a level up or pause keeps the current player
+    if !existing.is_empty() {
+        return;
+    }
+
     // Player
     let mut player = commands.spawn(SpriteBundle {
         sprite: Sprite {
@@ -33,10 +79,15 @@ pub fn spawn(mut commands: Commands) {
 
     player.insert((
         Player,
+        Level::default(),
+        Slots::new([Ability::Dash]),
         Experience {
             current: 0,
-            cap: 100,
+            cap: balance.experience.cap(1),
         },
+        RigidBody::Dynamic,
+        Velocity::default(),
+        LockedAxes::ROTATION_LOCKED,
         Collider::cuboid(15., 15.),
         GravityScale(0.),
         CollisionGroups::new(
@@ -47,7 +98,7 @@ pub fn spawn(mut commands: Commands) {
         ),
     ));
 
-    attribute::insert_common(&mut player);
+    attribute::insert_player(&mut player, &balance.player, &balance.player_only);
 }
 
 fn die(
@@ -61,15 +112,94 @@ fn die(
     }
 }
 
-/// Move player with WASD
+fn blink(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Invulnerable, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut invulnerable, mut sprite) in query.iter_mut() {
+        invulnerable.0.tick(time.delta());
+        if invulnerable.0.finished() {
+            sprite.color.set_a(1.);
+            commands.entity(entity).remove::<Invulnerable>();
+            continue;
+        }
+
+        let lit = ((invulnerable.0.elapsed_secs() / BLINK) as u32) % 2 == 1;
+        sprite.color.set_a(if lit { 1. } else { 0.3 });
+    }
+}
+
+/// Move with WASD, shoot with arrow keys and trigger abilities with [`ability::KEYS`]
+pub fn read_keyboard(keyboard_input: Res<Input<KeyCode>>, mut controls: ResMut<Controls>) {
+    let axis = |negative, positive| {
+        keyboard_input.pressed(positive) as i32 as f32
+            - keyboard_input.pressed(negative) as i32 as f32
+    };
+
+    controls.movement = Vec2::new(axis(KeyCode::A, KeyCode::D), axis(KeyCode::S, KeyCode::W));
+    controls.shoot = Vec2::new(
+        axis(KeyCode::Left, KeyCode::Right),
+        axis(KeyCode::Down, KeyCode::Up),
+    );
+    controls.ability = ability::KEYS
+        .iter()
+        .position(|&key| keyboard_input.just_pressed(key));
+}
+
+/// Dash [`DashDistance`] along the movement direction, untouchable until it ends
+fn dash(
+    mut commands: Commands,
+    mut events: EventReader<Activate>,
+    controls: Res<Controls>,
+    query: Query<(&DashDistance, Option<&Invulnerable>), (With<Player>, Without<Dashing>)>,
+    balance: Res<Balance>,
+) {
+    let direction = controls.movement.normalize_or_zero();
+    let Some(player) = events
+        .iter()
+        .filter(|event| event.ability == Ability::Dash)
+        .map(|event| event.user)
+        .last()
+    else {
+        return;
+    };
+    let Ok((distance, invulnerable)) = query.get(player) else {
+        return;
+    };
+
+    let duration = Duration::from_secs_f32(balance.dash_time);
+    let mut player = commands.entity(player);
+    player.insert(Dashing {
+        timer: Timer::new(duration, TimerMode::Once),
+        velocity: direction * distance.0 / balance.dash_time,
+    });
+    // Never cut a longer invulnerability short
+    match invulnerable {
+        Some(invulnerable) if invulnerable.0.remaining() >= duration => {}
+        _ => {
+            player.insert(Invulnerable(Timer::new(duration, TimerMode::Once)));
+        }
+    }
+}
+
+/// Drive the player's body at [`MoveSpeed`], as fast diagonally as straight
 fn move_player(
-    keyboard_input: Res<Input<KeyCode>>,
-    mut query: Query<(&mut Transform, &MoveSpeed), With<Player>>,
+    mut commands: Commands,
+    controls: Res<Controls>,
+    mut query: Query<(Entity, &mut Velocity, &MoveSpeed, Option<&mut Dashing>), With<Player>>,
+    time: Res<Time>,
 ) {
-    let (mut transform, speed) = query.single_mut();
+    let (player, mut velocity, speed, dashing) = query.single_mut();
+
+    if let Some(mut dashing) = dashing {
+        dashing.timer.tick(time.delta());
+        if !dashing.timer.finished() {
+            velocity.linvel = dashing.velocity;
+            return;
+        }
+        commands.entity(player).remove::<Dashing>();
+    }
 
-    transform.translation.x -= keyboard_input.pressed(KeyCode::A) as i32 as f32 * speed.0;
-    transform.translation.x += keyboard_input.pressed(KeyCode::D) as i32 as f32 * speed.0;
-    transform.translation.y += keyboard_input.pressed(KeyCode::W) as i32 as f32 * speed.0;
-    transform.translation.y -= keyboard_input.pressed(KeyCode::S) as i32 as f32 * speed.0;
+    velocity.linvel = controls.movement.normalize_or_zero() * speed.0;
 }
@@ -6,33 +6,45 @@ use bevy_rapier2d::prelude::*;
 use bevy_turborand::{DelegatedRng, GlobalRng};
 
 use crate::{
-    attribute::{AttackRange, AttackSpeedTimer, Damage, DupChance, MaxHealth},
+    attribute::{
+        self, Armor, AttackRange, AttackSpeedTimer, CritChance, CritMultiplier, Damage,
+        DamageEvent, DupChance, Knockback, MaxHealth,
+    },
+    balance::Balance,
     collision,
-    hostile::Hostile,
-    player::Player,
-    ui::OFFWHITE,
+    hostile::{self, HitStun, Hostile},
+    player::{ControlSet, Controls, Player},
+    run::RunRng,
+    ui::{OFFWHITE, YELLOW},
     GameState,
 };
 
 pub struct Plugin;
 impl prelude::Plugin for Plugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<ProjectileEvent>().add_systems(
-            (
-                spawn_projectile,
-                despawn_dead_projectiles,
-                handle_collision,
-                detect_collisions,
-                update_projectile_speed,
-            )
-                .in_set(OnUpdate(GameState::Game)),
-        );
+        app.init_resource::<Handles>()
+            .add_event::<ProjectileEvent>()
+            .add_event::<ShootEvent>()
+            .add_systems(
+                (
+                    spawn_projectile.after(ControlSet),
+                    despawn_dead_projectiles,
+                    // Knockback must not be overwritten by the chase in the same frame
+                    handle_collision.after(hostile::move_to_player),
+                    detect_collisions,
+                    update_projectile_speed,
+                    fade_crit_flashes,
+                )
+                    .in_set(OnUpdate(GameState::Game)),
+            );
     }
 }
 
-#[derive(Debug, Clone, Component)]
+#[derive(Debug, Default, Clone, Component)]
 pub struct Projectile {
     last_hit: Option<Entity>,
+    /// Unit vector the projectile travels along, hits knock back the same way
+    direction: Vec2,
 }
 #[derive(Debug, Clone, Component)]
 pub struct Parent(pub Entity);
@@ -46,35 +58,49 @@ pub struct ProjectileEvent {
 
 pub struct ShootEvent();
 
+/// Burst shown where a critical hit landed
+#[derive(Debug, Clone, Component)]
+pub struct CritFlash(pub Timer);
+
+/// Render handles shared by every projectile, left empty when running headless.
+#[derive(Resource)]
+struct Handles {
+    mesh: Handle<Mesh>,
+    material: Handle<ColorMaterial>,
+}
+
+impl FromWorld for Handles {
+    fn from_world(world: &mut World) -> Self {
+        let mut handles = Self {
+            mesh: default(),
+            material: default(),
+        };
+        if let Some(mut meshes) = world.get_resource_mut::<Assets<Mesh>>() {
+            handles.mesh = meshes.add(shape::Circle::new(5.).into());
+        }
+        if let Some(mut materials) = world.get_resource_mut::<Assets<ColorMaterial>>() {
+            let color: prelude::Color = OFFWHITE.into();
+            handles.material = materials.add(ColorMaterial::from(color));
+        }
+        handles
+    }
+}
+
 fn spawn_projectile(
-    keyboard_input: Res<Input<KeyCode>>,
+    controls: Res<Controls>,
     mut player: Query<(Entity, &Transform, &ProjectileSpeed, &mut AttackSpeedTimer), With<Player>>,
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    handles: Res<Handles>,
     mut ev_shoot: EventWriter<ShootEvent>,
 ) {
     let (_player_entity, player_transform, projectile_speed, mut timer) = player.single_mut();
-    // Determine direction of projectile base on arrow keys on keyboard
-    let mut direction = Vec2::new(0.0, 0.0);
-    direction.x -= keyboard_input.pressed(KeyCode::Left) as i32 as f32;
-    direction.x += keyboard_input.pressed(KeyCode::Right) as i32 as f32;
-    direction.y += keyboard_input.pressed(KeyCode::Up) as i32 as f32;
-    direction.y -= keyboard_input.pressed(KeyCode::Down) as i32 as f32;
-    direction *= projectile_speed.0;
+    let direction = controls.shoot * projectile_speed.0;
 
     if direction == Vec2::ZERO || !timer.0.finished() {
         return;
     }
 
-    spawn(
-        &mut commands,
-        &mut meshes,
-        &mut materials,
-        direction,
-        player_transform,
-        None,
-    );
+    spawn(&mut commands, &handles, direction, player_transform, None);
 
     timer.0.reset();
     ev_shoot.send(ShootEvent());
@@ -82,17 +108,15 @@ fn spawn_projectile(
 
 fn spawn(
     commands: &mut Commands,
-    meshes: &mut Assets<Mesh>,
-    materials: &mut Assets<ColorMaterial>,
+    handles: &Handles,
     direction: Vec2,
     begin: &Transform,
     last_hit: Option<Entity>,
 ) {
-    let color: prelude::Color = OFFWHITE.into();
     commands
         .spawn(MaterialMesh2dBundle {
-            mesh: meshes.add(shape::Circle::new(5.).into()).into(),
-            material: materials.add(ColorMaterial::from(color)),
+            mesh: handles.mesh.clone().into(),
+            material: handles.material.clone(),
             ..default()
         })
         .insert((
@@ -101,7 +125,10 @@ fn spawn(
             GravityScale(0.0),
             Velocity::linear(direction),
             TransformBundle::from(*begin),
-            Projectile { last_hit },
+            Projectile {
+                last_hit,
+                direction: direction.normalize_or_zero(),
+            },
             CollisionGroups::new(
                 collision::group::PLAYER_PROJECTILE,
                 collision::group::HOSTILE,
@@ -157,48 +184,104 @@ fn despawn_dead_projectiles(
 
 #[allow(clippy::too_many_arguments)]
 fn handle_collision(
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    handles: Res<Handles>,
     mut commands: Commands,
-    mut projectiles: Query<(&Transform, &Projectile, &mut Velocity)>,
+    mut projectiles: Query<(&Transform, &mut Projectile, &mut Velocity)>,
     mut events: EventReader<ProjectileEvent>,
-    mut health: Query<&mut MaxHealth>,
-    mut rng: ResMut<GlobalRng>,
-    damage: Query<&Damage, With<Player>>,
-    player: Query<(&DupChance, &ProjectileSpeed), With<Player>>,
+    mut health: Query<(&mut MaxHealth, &Armor, &Transform, &mut Velocity), Without<Projectile>>,
+    mut rng: ResMut<RunRng>,
+    damage: Query<(&Damage, &CritChance, &CritMultiplier, &Knockback), With<Player>>,
+    player: Query<(Entity, &DupChance, &ProjectileSpeed), With<Player>>,
+    mut damage_writer: EventWriter<DamageEvent>,
+    balance: Res<Balance>,
 ) {
-    let (chance, proj_speed) = player.single();
-    let damage = damage.single().0;
+    let rng = &mut rng.hits;
+    let (player, chance, proj_speed) = player.single();
+    let (damage, crit_chance, crit_multiplier, knockback) = damage.single();
     for event in events.iter() {
-        let Ok((transform, Projectile { last_hit }, mut velocity)) = projectiles.get_mut(event.projectile) else {
+        let Ok((transform, mut projectile, mut velocity)) = projectiles.get_mut(event.projectile)
+        else {
             continue;
         };
 
         if rng.f32() < chance.0 {
-            let direction = random_direction(&mut rng) * proj_speed.0;
+            let direction = random_direction(rng) * proj_speed.0;
             spawn(
                 &mut commands,
-                &mut meshes,
-                &mut materials,
+                &handles,
                 direction,
                 transform,
                 Some(event.target),
             );
         }
 
-        if Some(event.target) == *last_hit {
-            velocity.linvel = random_direction(&mut rng) * proj_speed.0;
+        if Some(event.target) == projectile.last_hit {
+            projectile.direction = random_direction(rng);
+            velocity.linvel = projectile.direction * proj_speed.0;
             continue;
         }
 
-        if let Ok(mut health) = health.get_mut(event.target) {
-            health.0 = health.0.saturating_sub(damage);
+        if let Ok((mut health, armor, target, mut target_velocity)) = health.get_mut(event.target) {
+            let crit = rng.f32() < crit_chance.0;
+            let damage = if crit {
+                (damage.0 as f32 * crit_multiplier.0).round() as i32
+            } else {
+                damage.0
+            };
+            let amount = attribute::mitigate(damage, armor, &balance);
+            health.0 = health.0.saturating_sub(amount);
+            damage_writer.send(DamageEvent {
+                source: player,
+                target: event.target,
+                amount,
+                crit,
+            });
+
+            if knockback.0 > 0. {
+                target_velocity.linvel += projectile.direction * knockback.0;
+                commands
+                    .entity(event.target)
+                    .insert(HitStun(Timer::from_seconds(
+                        balance.hit_stun,
+                        TimerMode::Once,
+                    )));
+            }
+
+            if crit {
+                commands.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: YELLOW.into(),
+                            custom_size: Some(Vec2::new(40.0, 40.0)),
+                            ..default()
+                        },
+                        transform: Transform::from_translation(target.translation + Vec3::Z),
+                        ..default()
+                    },
+                    CritFlash(Timer::from_seconds(0.15, TimerMode::Once)),
+                ));
+            }
         }
 
         commands.entity(event.projectile).despawn();
     }
 }
 
+fn fade_crit_flashes(
+    mut commands: Commands,
+    mut flashes: Query<(Entity, &mut CritFlash, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (entity, mut flash, mut transform) in flashes.iter_mut() {
+        flash.0.tick(time.delta());
+        if flash.0.finished() {
+            commands.entity(entity).despawn();
+        } else {
+            transform.scale = Vec3::splat(1. + flash.0.percent());
+        }
+    }
+}
+
 fn update_projectile_speed(
     mut projectiles: Query<&mut Velocity, (With<Projectile>, Changed<Velocity>)>,
     mut player: Query<&mut ProjectileSpeed, With<Player>>,
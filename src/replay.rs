@@ -0,0 +1,204 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::{
+    app::AppExit,
+    ecs::schedule::ExecutorKind,
+    prelude::{self, *},
+    time::TimeUpdateStrategy,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ability, buff,
+    player::{self, ControlSet, Controls},
+    run::{RestartEvent, Seed, SeedConfig},
+    save, GameState,
+};
+
+pub struct Plugin;
+impl prelude::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            play_frame
+                .in_set(ControlSet)
+                .after(player::read_keyboard)
+                .run_if(resource_exists::<Playback>()),
+        )
+        .add_system(
+            step_clock
+                .in_base_set(CoreSet::Last)
+                .run_if(resource_exists::<Playback>()),
+        )
+        .add_system(
+            record_frame
+                .in_base_set(CoreSet::Last)
+                .run_if(resource_exists::<Recorder>()),
+        )
+        .add_system(
+            save.in_schedule(OnEnter(GameState::Dead))
+                .run_if(resource_exists::<Recorder>()),
+        )
+        .add_system(
+            save_on_exit
+                .in_base_set(CoreSet::Last)
+                .after(record_frame)
+                .run_if(resource_exists::<Recorder>()),
+        );
+    }
+}
+
+/// Everything needed to play a run back exactly: its seed and the input of every
+/// simulated frame.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub frames: Vec<Frame>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Frame {
+    pub delta: Duration,
+    pub movement: [f32; 2],
+    pub shoot: [f32; 2],
+    /// Slot whose ability was triggered
+    pub ability: Option<usize>,
+    /// Every pair picked, in order
+    pub selects: Vec<usize>,
+    /// Every slot an offered ability was learned into, in order
+    pub learns: Vec<usize>,
+}
+
+/// Records every run to a replay file, overwritten when the run ends
+#[derive(Resource)]
+pub struct Recorder {
+    path: PathBuf,
+    replay: Replay,
+}
+
+/// Feeds a recorded run back through the game instead of the keyboard
+#[derive(Resource)]
+pub struct Playback {
+    replay: Replay,
+    cursor: usize,
+}
+
+/// Record every run of the app to `path`
+pub fn record(app: &mut App, path: impl Into<PathBuf>) {
+    deterministic(app);
+    app.insert_resource(Recorder {
+        path: path.into(),
+        replay: default(),
+    });
+}
+
+/// Start the app straight into the run recorded at `path`
+pub fn replay(app: &mut App, path: impl AsRef<Path>) -> io::Result<()> {
+    let replay: Replay = save::read_ron(path.as_ref())?;
+
+    deterministic(app);
+    app.insert_resource(SeedConfig(Some(replay.seed)))
+        .insert_resource(Playback { replay, cursor: 0 });
+    app.world.send_event(RestartEvent);
+    Ok(())
+}
+
+/// Systems racing for the same data must run in the same order every frame, both when
+/// recording and when playing back.
+fn deterministic(app: &mut App) {
+    app.edit_schedule(CoreSchedule::Main, |schedule| {
+        schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    });
+}
+
+fn is_simulated(game_state: &State<GameState>) -> bool {
+    matches!(game_state.0, GameState::Game | GameState::LevelUp)
+}
+
+fn play_frame(
+    mut commands: Commands,
+    mut playback: ResMut<Playback>,
+    mut controls: ResMut<Controls>,
+    mut writer: EventWriter<buff::Select>,
+    mut learn_writer: EventWriter<ability::Learn>,
+    game_state: Res<State<GameState>>,
+) {
+    if !is_simulated(&game_state) {
+        return;
+    }
+
+    let Some(frame) = playback.replay.frames.get(playback.cursor) else {
+        info!("Replay finished, handing control back to the keyboard");
+        commands.remove_resource::<Playback>();
+        commands.insert_resource(TimeUpdateStrategy::Automatic);
+        return;
+    };
+
+    controls.movement = frame.movement.into();
+    controls.shoot = frame.shoot.into();
+    controls.ability = frame.ability;
+    writer.send_batch(frame.selects.iter().map(|&index| buff::Select(index)));
+    learn_writer.send_batch(frame.learns.iter().map(|&slot| ability::Learn(slot)));
+    playback.cursor += 1;
+}
+
+/// Make the next frame last exactly as long as it did when recorded
+fn step_clock(playback: Res<Playback>, time: Res<Time>, mut strategy: ResMut<TimeUpdateStrategy>) {
+    let (Some(frame), Some(last)) = (
+        playback.replay.frames.get(playback.cursor),
+        time.last_update(),
+    ) else {
+        return;
+    };
+
+    *strategy = TimeUpdateStrategy::ManualInstant(last + frame.delta);
+}
+
+fn record_frame(
+    mut recorder: ResMut<Recorder>,
+    seed: Res<Seed>,
+    controls: Res<Controls>,
+    time: Res<Time>,
+    mut selects: EventReader<buff::Select>,
+    mut learns: EventReader<ability::Learn>,
+    game_state: Res<State<GameState>>,
+) {
+    // Every run reseeds, starting a fresh recording
+    if seed.is_changed() {
+        recorder.replay = Replay {
+            seed: seed.0,
+            frames: Vec::new(),
+        };
+    }
+
+    let selects = selects.iter().map(|select| select.0).collect();
+    let learns = learns.iter().map(|learn| learn.0).collect();
+    if !is_simulated(&game_state) {
+        return;
+    }
+
+    recorder.replay.frames.push(Frame {
+        delta: time.delta(),
+        movement: controls.movement.into(),
+        shoot: controls.shoot.into(),
+        ability: controls.ability,
+        selects,
+        learns,
+    });
+}
+
+fn save(recorder: Res<Recorder>) {
+    match save::write_ron(&recorder.path, &recorder.replay) {
+        Ok(()) => info!("Saved replay to {}", recorder.path.display()),
+        Err(err) => error!("Failed to save replay: {err}"),
+    }
+}
+
+fn save_on_exit(recorder: Res<Recorder>, mut exit: EventReader<AppExit>) {
+    if exit.iter().count() > 0 {
+        save(recorder);
+    }
+}
@@ -0,0 +1,101 @@
+use std::marker::PhantomData;
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::{self, *},
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::de::DeserializeOwned;
+
+/// A RON file in `assets` mirrored into the resource of the same type, reloaded whenever
+/// the file changes
+pub trait RonAsset: Resource + TypeUuid + Clone + Default + DeserializeOwned {
+    /// File under `assets`, everything past its first dot is the extension it loads from
+    const PATH: &'static str;
+
+    /// Checked once parsed, rejected files keep the previous value around
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+pub struct Plugin<T>(PhantomData<T>);
+
+impl<T> Default for Plugin<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: RonAsset> prelude::Plugin for Plugin<T> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<T>();
+
+        // Headless apps have no asset server and keep the defaults
+        if !app.world.contains_resource::<AssetServer>() {
+            return;
+        }
+
+        app.add_asset::<T>()
+            .add_asset_loader(Loader::<T> {
+                extensions: [T::PATH.split_once('.').map_or(T::PATH, |(_, ext)| ext)],
+                marker: PhantomData,
+            })
+            .add_startup_system(load::<T>)
+            .add_system(reload::<T>);
+    }
+}
+
+/// Keeps the file loaded so it can be watched
+#[derive(Resource)]
+struct Source<T: RonAsset>(Handle<T>);
+
+struct Loader<T> {
+    extensions: [&'static str; 1],
+    marker: PhantomData<T>,
+}
+
+impl<T: RonAsset> AssetLoader for Loader<T> {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let asset: T = ron::de::from_bytes(bytes)?;
+            asset.validate().map_err(bevy::asset::Error::msg)?;
+            load_context.set_default_asset(LoadedAsset::new(asset));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &self.extensions
+    }
+}
+
+fn load<T: RonAsset>(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(Source::<T>(asset_server.load(T::PATH)));
+}
+
+fn reload<T: RonAsset>(
+    mut events: EventReader<AssetEvent<T>>,
+    source: Option<Res<Source<T>>>,
+    assets: Res<Assets<T>>,
+    mut resource: ResMut<T>,
+) {
+    for event in events.iter() {
+        let (AssetEvent::Created { handle } | AssetEvent::Modified { handle }) = event else {
+            continue;
+        };
+        if source.as_ref().map(|source| &source.0) != Some(handle) {
+            continue;
+        }
+
+        if let Some(loaded) = assets.get(handle) {
+            *resource = loaded.clone();
+            info!("Loaded {}", T::PATH);
+        }
+    }
+}
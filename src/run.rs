@@ -0,0 +1,239 @@
+use bevy::prelude::{self, *};
+use bevy_turborand::{DelegatedRng, GlobalRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ability::{NovaFlash, TimeSlow},
+    buff,
+    hostile::{Hostile, Score, SpawnRate, SpawnTimer},
+    loot::{self, Loot},
+    player::Player,
+    projectile::{CritFlash, Projectile},
+    stats::RunStats,
+    GameState,
+};
+
+pub struct Plugin;
+impl prelude::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<GameState>()
+            .init_resource::<SeedConfig>()
+            .init_resource::<Seed>()
+            .init_resource::<RunRng>()
+            .add_event::<RestartEvent>()
+            .add_system(reset);
+    }
+}
+
+/// Starts a new run, whether from the main menu or after a previous one
+pub struct RestartEvent;
+
+/// Seed pinned up front, e.g. with `--seed` on the command line. When unset every run
+/// picks its own.
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct SeedConfig(pub Option<u64>);
+
+/// Seed of the current run
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct Seed(pub u64);
+
+/// Random streams of the current run, forked from its [`Seed`]. Each system draws from
+/// its own stream, so the order systems happen to run in never changes the rolls.
+#[derive(Debug, Default, Clone, Resource, Serialize, Deserialize)]
+pub struct RunRng {
+    pub spawn: GlobalRng,
+    pub loot: GlobalRng,
+    pub hits: GlobalRng,
+    pub choices: GlobalRng,
+}
+
+impl RunRng {
+    pub fn from_seed(seed: u64) -> Self {
+        let mut root = GlobalRng::with_seed(seed);
+        Self {
+            spawn: GlobalRng::with_seed(root.u64(..)),
+            loot: GlobalRng::with_seed(root.u64(..)),
+            hits: GlobalRng::with_seed(root.u64(..)),
+            choices: GlobalRng::with_seed(root.u64(..)),
+        }
+    }
+}
+
+/// Put every run resource and entity back to a fresh-run state. The player is
+/// respawned when entering [`GameState::Game`].
+#[allow(clippy::too_many_arguments)]
+fn reset(
+    mut events: EventReader<RestartEvent>,
+    entities: Query<
+        Entity,
+        Or<(
+            With<Player>,
+            With<Hostile>,
+            With<Loot>,
+            With<Projectile>,
+            With<CritFlash>,
+            With<NovaFlash>,
+        )>,
+    >,
+    mut commands: Commands,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut score: ResMut<Score>,
+    mut spawn_rate: ResMut<SpawnRate>,
+    mut spawn_timer: ResMut<SpawnTimer>,
+    mut choices: ResMut<buff::Choices>,
+    mut stats: ResMut<RunStats>,
+    mut loot_events: ResMut<Events<loot::Event>>,
+    seed_config: Res<SeedConfig>,
+    mut seed: ResMut<Seed>,
+    mut global_rng: ResMut<GlobalRng>,
+    mut run_rng: ResMut<RunRng>,
+) {
+    if events.iter().count() == 0 {
+        return;
+    }
+
+    for entity in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    *score = default();
+    *spawn_rate = default();
+    *spawn_timer = default();
+    *choices = default();
+    *stats = default();
+    loot_events.clear();
+    commands.remove_resource::<TimeSlow>();
+
+    seed.0 = seed_config.0.unwrap_or_else(|| global_rng.u64(..));
+    *global_rng = GlobalRng::with_seed(seed.0);
+    *run_rng = RunRng::from_seed(seed.0);
+
+    game_state.set(GameState::Game);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::input::InputPlugin;
+    use bevy_turborand::RngPlugin;
+
+    use super::*;
+    use crate::{
+        ability,
+        attribute::{Experience, Health},
+        balance::Balance,
+        buff::{Affect, Diff},
+        player,
+    };
+
+    #[derive(Debug, PartialEq)]
+    struct Snapshot {
+        score: u128,
+        spawn_rate: Duration,
+        spawn_timer: (Duration, Duration),
+        seed: u64,
+        rolls: [u64; 4],
+        choices: (usize, u32),
+        player: (i32, u32, u32),
+        entities: (usize, usize, usize, usize),
+    }
+
+    fn snapshot(app: &mut App) -> Snapshot {
+        let world = &mut app.world;
+        let mut player = world.query_filtered::<(&Health, &Experience), With<Player>>();
+        let (health, xp) = player.single(world);
+        let timer = &world.resource::<SpawnTimer>().0;
+        let choices = world.resource::<buff::Choices>();
+        let mut rng = world.resource::<RunRng>().clone();
+
+        Snapshot {
+            score: world.resource::<Score>().0,
+            spawn_rate: world.resource::<SpawnRate>().0,
+            spawn_timer: (timer.duration(), timer.elapsed()),
+            seed: world.resource::<Seed>().0,
+            rolls: [
+                rng.spawn.u64(..),
+                rng.loot.u64(..),
+                rng.hits.u64(..),
+                rng.choices.u64(..),
+            ],
+            choices: (choices.inner.len(), choices.remaining),
+            player: (health.0, xp.current, xp.cap),
+            entities: (
+                world
+                    .query_filtered::<(), With<Player>>()
+                    .iter(world)
+                    .count(),
+                world
+                    .query_filtered::<(), With<Hostile>>()
+                    .iter(world)
+                    .count(),
+                world.query_filtered::<(), With<Loot>>().iter(world).count(),
+                world
+                    .query_filtered::<(), With<Projectile>>()
+                    .iter(world)
+                    .count(),
+            ),
+        }
+    }
+
+    fn start(app: &mut App) {
+        app.update();
+        app.update();
+    }
+
+    #[test]
+    fn restart_matches_fresh_run() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(InputPlugin)
+            .add_plugin(RngPlugin::default())
+            .add_plugin(Plugin)
+            .add_event::<loot::Event>()
+            .add_event::<ability::Activate>()
+            .init_resource::<Score>()
+            .init_resource::<SpawnRate>()
+            .init_resource::<SpawnTimer>()
+            .init_resource::<buff::Choices>()
+            .init_resource::<RunStats>()
+            .init_resource::<Balance>()
+            .add_plugin(player::Plugin)
+            .insert_resource(SeedConfig(Some(42)));
+
+        app.world.send_event(RestartEvent);
+        start(&mut app);
+        let fresh = snapshot(&mut app);
+
+        // Dirty everything a run can touch
+        let world = &mut app.world;
+        world.resource_mut::<Score>().0 = 500;
+        world.resource_mut::<SpawnRate>().0 = Duration::from_millis(100);
+        world
+            .resource_mut::<SpawnTimer>()
+            .0
+            .tick(Duration::from_millis(700));
+        let diff = Diff {
+            affect: Affect::Damage,
+            value: 0.5,
+        };
+        let mut choices = world.resource_mut::<buff::Choices>();
+        choices.inner.push((diff, -diff));
+        choices.remaining = 2;
+        world.resource_mut::<RunRng>().spawn.u64(..);
+        world.spawn(Hostile);
+        world.spawn(Loot::Experience);
+        world.spawn(Projectile::default());
+        world.send_event(loot::Event(Vec3::ZERO));
+        let mut player = world.query_filtered::<&mut Health, With<Player>>();
+        player.single_mut(world).0 = 0;
+        app.insert_resource(NextState(Some(GameState::Dead)));
+        app.update();
+
+        app.world.send_event(RestartEvent);
+        start(&mut app);
+
+        assert_eq!(app.world.resource::<State<GameState>>().0, GameState::Game);
+        assert_eq!(snapshot(&mut app), fresh);
+    }
+}
@@ -0,0 +1,398 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::prelude::{self, *};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ability::{Ability, Slot, Slots, TimeSlow},
+    attribute::{AttackSpeedTimer, Experience, Health, Level, MaxHealth},
+    balance::Balance,
+    buff,
+    hostile::{self, Hostile, Score, SpawnRate, SpawnTimer},
+    modifier::{Modifiers, Timed},
+    player::Player,
+    run::{RestartEvent, RunRng, Seed},
+    stats::RunStats,
+    GameState,
+};
+
+pub struct Plugin;
+impl prelude::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveExists>()
+            .add_event::<SaveEvent>()
+            .add_event::<LoadEvent>()
+            .add_system(check.in_schedule(OnEnter(GameState::MainMenu)))
+            // Saving usually comes right before quitting, so catch it within the frame
+            .add_system(write.in_base_set(CoreSet::Last))
+            .add_system(load)
+            .add_system(
+                resume
+                    .in_set(OnUpdate(GameState::Game))
+                    .run_if(resource_exists::<Resume>()),
+            );
+    }
+}
+
+/// Whether a run is saved at [`path`], checked whenever the main menu opens
+#[derive(Debug, Default, Resource)]
+pub struct SaveExists(pub bool);
+
+/// Save the run in progress to [`path`]
+pub struct SaveEvent;
+/// Continue the run saved at [`path`]
+pub struct LoadEvent;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Save {
+    pub player: PlayerSave,
+    pub hostiles: Vec<HostileSave>,
+    pub score: u128,
+    pub spawn_rate: Duration,
+    /// Time since the last spawn
+    pub spawn_timer: Duration,
+    pub choices: buff::Choices,
+    pub stats: RunStats,
+    pub seed: u64,
+    pub rng: RunRng,
+    /// Time left until hostiles stop being slowed, zero while they aren't
+    pub time_slow: Duration,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerSave {
+    pub position: [f32; 2],
+    pub health: i32,
+    pub experience: (u32, u32),
+    /// Duration and elapsed time of the attack timer
+    pub attack_timer: (Duration, Duration),
+    /// Every other attribute is recomputed from these
+    pub modifiers: Modifiers,
+    pub timed: Timed,
+    /// Abilities in slot order, with the duration and elapsed time of their cooldowns
+    pub abilities: Vec<(Ability, (Duration, Duration))>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostileSave {
+    pub position: [f32; 2],
+    pub health: i32,
+    /// Duration and elapsed time of the attack timer
+    pub attack_timer: (Duration, Duration),
+}
+
+/// Save waiting to be applied once the fresh run has spawned its player
+#[derive(Resource)]
+struct Resume(Save);
+
+/// Directory holding everything the game persists
+pub fn data_dir() -> PathBuf {
+    ProjectDirs::from("", "", "bevy-jam-3")
+        .map(|dirs| dirs.data_dir().to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+pub fn path() -> PathBuf {
+    data_dir().join("save.ron")
+}
+
+/// Serialize `value` as RON to `path`, creating missing directories
+pub fn write_ron(path: &Path, value: &impl Serialize) -> io::Result<()> {
+    let ron = ron::ser::to_string_pretty(value, default())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, ron)
+}
+
+pub fn read_ron<T: for<'de> Deserialize<'de>>(path: &Path) -> io::Result<T> {
+    ron::from_str(&fs::read_to_string(path)?)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write(
+    mut events: EventReader<SaveEvent>,
+    player: Query<
+        (
+            &Transform,
+            &Health,
+            &Experience,
+            &Modifiers,
+            &Timed,
+            &Slots,
+            &AttackSpeedTimer,
+        ),
+        With<Player>,
+    >,
+    hostiles: Query<(&Transform, &MaxHealth, &AttackSpeedTimer), With<Hostile>>,
+    score: Res<Score>,
+    spawn_rate: Res<SpawnRate>,
+    spawn_timer: Res<SpawnTimer>,
+    choices: Res<buff::Choices>,
+    stats: Res<RunStats>,
+    seed: Res<Seed>,
+    rng: Res<RunRng>,
+    slow: Option<Res<TimeSlow>>,
+    mut exists: ResMut<SaveExists>,
+) {
+    if events.iter().count() == 0 {
+        return;
+    }
+
+    let Ok((transform, health, experience, modifiers, timed, slots, attack_timer)) =
+        player.get_single()
+    else {
+        return;
+    };
+
+    let save = Save {
+        player: PlayerSave {
+            position: transform.translation.truncate().into(),
+            health: health.0,
+            experience: (experience.current, experience.cap),
+            attack_timer: timer_state(&attack_timer.0),
+            modifiers: modifiers.clone(),
+            timed: timed.clone(),
+            abilities: slots
+                .0
+                .iter()
+                .map(|slot| (slot.ability, timer_state(&slot.cooldown)))
+                .collect(),
+        },
+        hostiles: hostiles
+            .iter()
+            .map(|(transform, health, attack_timer)| HostileSave {
+                position: transform.translation.truncate().into(),
+                health: health.0,
+                attack_timer: timer_state(&attack_timer.0),
+            })
+            .collect(),
+        score: score.0,
+        spawn_rate: spawn_rate.0,
+        spawn_timer: spawn_timer.0.elapsed(),
+        choices: choices.clone(),
+        stats: stats.clone(),
+        seed: seed.0,
+        rng: rng.clone(),
+        time_slow: slow.map_or(Duration::ZERO, |slow| slow.0.remaining()),
+    };
+
+    match write_ron(&path(), &save) {
+        Ok(()) => {
+            exists.0 = true;
+            info!("Saved run to {}", path().display());
+        }
+        Err(err) => error!("Failed to save run: {err}"),
+    }
+}
+
+fn check(mut exists: ResMut<SaveExists>) {
+    exists.0 = path().exists();
+}
+
+fn load(
+    mut events: EventReader<LoadEvent>,
+    mut commands: Commands,
+    mut writer: EventWriter<RestartEvent>,
+    mut exists: ResMut<SaveExists>,
+) {
+    if events.iter().count() == 0 {
+        return;
+    }
+
+    let save = match read_ron(&path()) {
+        Ok(save) => save,
+        Err(err) => {
+            error!("Failed to load run: {err}");
+            return;
+        }
+    };
+
+    // A save is continued once, dying still ends the run for good
+    if let Err(err) = fs::remove_file(path()) {
+        warn!("Failed to remove save: {err}");
+    }
+    exists.0 = path().exists();
+
+    commands.insert_resource(Resume(save));
+    writer.send(RestartEvent);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn resume(
+    mut commands: Commands,
+    resume: Res<Resume>,
+    mut player: Query<
+        (
+            &mut Transform,
+            &mut Health,
+            &mut Experience,
+            &mut Level,
+            &mut Modifiers,
+            &mut Timed,
+            &mut Slots,
+            &mut AttackSpeedTimer,
+        ),
+        With<Player>,
+    >,
+    mut score: ResMut<Score>,
+    mut spawn_rate: ResMut<SpawnRate>,
+    mut spawn_timer: ResMut<SpawnTimer>,
+    mut choices: ResMut<buff::Choices>,
+    mut stats: ResMut<RunStats>,
+    mut seed: ResMut<Seed>,
+    mut rng: ResMut<RunRng>,
+    mut game_state: ResMut<NextState<GameState>>,
+    balance: Res<Balance>,
+) {
+    let Ok((
+        mut transform,
+        mut health,
+        mut experience,
+        mut level,
+        mut modifiers,
+        mut timed,
+        mut slots,
+        mut attack_timer,
+    )) = player.get_single_mut()
+    else {
+        return;
+    };
+
+    let Resume(save) = &*resume;
+    let saved = &save.player;
+    transform.translation = Vec2::from(saved.position).extend(transform.translation.z);
+    health.0 = saved.health;
+    (experience.current, experience.cap) = saved.experience;
+    attack_timer.0 = restore_timer(saved.attack_timer);
+    // Every level up is counted in the stats
+    level.0 = save.stats.level();
+    *modifiers = saved.modifiers.clone();
+    *timed = saved.timed.clone();
+    slots.0 = saved
+        .abilities
+        .iter()
+        .map(|&(ability, cooldown)| Slot {
+            ability,
+            cooldown: restore_timer(cooldown),
+        })
+        .collect();
+
+    for hostile in save.hostiles.iter() {
+        let entity = hostile::spawn_at(
+            &mut commands,
+            Vec2::from(hostile.position).extend(0.),
+            &balance.hostile,
+        );
+        commands.entity(entity).insert((
+            MaxHealth(hostile.health),
+            AttackSpeedTimer(restore_timer(hostile.attack_timer)),
+        ));
+    }
+
+    score.0 = save.score;
+    spawn_rate.0 = save.spawn_rate;
+    spawn_timer.0.set_duration(save.spawn_rate);
+    spawn_timer.0.set_elapsed(save.spawn_timer);
+    *choices = save.choices.clone();
+    *stats = save.stats.clone();
+    seed.0 = save.seed;
+    *rng = save.rng.clone();
+    if !save.time_slow.is_zero() {
+        commands.insert_resource(TimeSlow(Timer::new(save.time_slow, TimerMode::Once)));
+    }
+
+    if choices.remaining > 0 {
+        game_state.set(GameState::LevelUp);
+    }
+
+    commands.remove_resource::<Resume>();
+}
+
+/// Duration and elapsed time of `timer`
+fn timer_state(timer: &Timer) -> (Duration, Duration) {
+    (timer.duration(), timer.elapsed())
+}
+
+fn restore_timer((duration, elapsed): (Duration, Duration)) -> Timer {
+    let mut timer = Timer::new(duration, TimerMode::Once);
+    timer.set_elapsed(elapsed);
+    timer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        attribute::{Damage, MoveSpeed},
+        buff::{Affect, Apply, Diff},
+        headless_app,
+        modifier::Source,
+    };
+
+    #[test]
+    fn resumed_run_keeps_upgrades() {
+        let mut app = headless_app();
+        let mut player = app.world.query_filtered::<Entity, With<Player>>();
+        while player.iter(&app.world).next().is_none() {
+            app.update();
+        }
+        let entity = player.single(&app.world);
+        for affect in [Affect::Damage, Affect::MoveSpeed] {
+            app.world.send_event(Apply {
+                diff: Diff { affect, value: 0.5 },
+                source: Source::Upgrade(0),
+                target: entity,
+                duration: None,
+            });
+        }
+        app.update();
+
+        let world = &app.world;
+        let damage = world.get::<Damage>(entity).unwrap().0;
+        let speed = world.get::<MoveSpeed>(entity).unwrap().0;
+        assert!(damage > Balance::default().player.damage);
+        let save = Save {
+            player: PlayerSave {
+                position: [0., 0.],
+                health: world.get::<Health>(entity).unwrap().0,
+                experience: (0, 100),
+                attack_timer: default(),
+                modifiers: world.get::<Modifiers>(entity).unwrap().clone(),
+                timed: default(),
+                abilities: Vec::new(),
+            },
+            hostiles: Vec::new(),
+            score: 0,
+            spawn_rate: SpawnRate::default().0,
+            spawn_timer: Duration::ZERO,
+            choices: default(),
+            stats: default(),
+            seed: 0,
+            rng: RunRng::from_seed(0),
+            time_slow: Duration::ZERO,
+        };
+
+        // Resumed in the frame the fresh player spawns
+        let mut app = headless_app();
+        app.insert_resource(Resume(save));
+        while app.world.contains_resource::<Resume>() {
+            app.update();
+        }
+        app.update();
+
+        let mut player = app
+            .world
+            .query_filtered::<(&Damage, &MoveSpeed), With<Player>>();
+        let (resumed_damage, resumed_speed) = player.single(&app.world);
+        assert_eq!(resumed_damage.0, damage);
+        assert_eq!(resumed_speed.0, speed);
+    }
+}
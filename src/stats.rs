@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use bevy::prelude::{self, *};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ability::Ability,
+    attribute::{DamageEvent, LevelUpEvent},
+    buff,
+    hostile::EnemyDeathEvent,
+    player::Player,
+    projectile::ShootEvent,
+    GameState,
+};
+
+pub struct Plugin;
+impl prelude::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunStats>()
+            .add_system(survive.in_set(OnUpdate(GameState::Game)))
+            .add_system(count_levels)
+            .add_system(count_kills)
+            .add_system(count_shots)
+            .add_system(count_damage)
+            .add_system(count_upgrades);
+    }
+}
+
+/// Running tally of the current run, reset along with it
+#[derive(Debug, Default, Clone, Resource, Serialize, Deserialize)]
+pub struct RunStats {
+    pub survived: Duration,
+    pub levels: u32,
+    pub kills: u32,
+    pub shots: u32,
+    pub damage_dealt: i64,
+    pub damage_taken: i64,
+    /// Every permanent buff and debuff applied to the player, in order
+    pub upgrades: Vec<buff::Diff>,
+    /// Every ability learned on a level up, in order
+    pub abilities: Vec<Ability>,
+}
+
+impl RunStats {
+    /// Level reached, runs start at level 1
+    pub fn level(&self) -> u32 {
+        self.levels + 1
+    }
+}
+
+fn survive(mut stats: ResMut<RunStats>, time: Res<Time>) {
+    stats.survived += time.delta();
+}
+
+fn count_levels(mut stats: ResMut<RunStats>, mut events: EventReader<LevelUpEvent>) {
+    stats.levels += events.iter().count() as u32;
+}
+
+fn count_kills(mut stats: ResMut<RunStats>, mut events: EventReader<EnemyDeathEvent>) {
+    stats.kills += events.iter().count() as u32;
+}
+
+fn count_shots(mut stats: ResMut<RunStats>, mut events: EventReader<ShootEvent>) {
+    stats.shots += events.iter().count() as u32;
+}
+
+fn count_damage(
+    mut stats: ResMut<RunStats>,
+    mut events: EventReader<DamageEvent>,
+    player: Query<Entity, With<Player>>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    for event in events.iter() {
+        if event.source == player {
+            stats.damage_dealt += i64::from(event.amount);
+        }
+        if event.target == player {
+            stats.damage_taken += i64::from(event.amount);
+        }
+    }
+}
+
+fn count_upgrades(
+    mut stats: ResMut<RunStats>,
+    mut events: EventReader<buff::Apply>,
+    player: Query<Entity, With<Player>>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    stats.upgrades.extend(
+        events
+            .iter()
+            .filter(|event| event.target == player && event.duration.is_none())
+            .map(|event| event.diff),
+    );
+}
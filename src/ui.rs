@@ -1,12 +1,25 @@
-use bevy::prelude::{self, *};
+use std::time::Duration;
+
+use bevy::{
+    app::AppExit,
+    prelude::{self, *},
+};
 use bevy_egui::{egui, EguiContexts};
-use bevy_turborand::GlobalRng;
 
 use crate::{
+    ability::{self, Slots},
     attribute::{Health, MaxHealth},
+    audio::Volume,
+    balance::Balance,
     buff,
+    highscore::HighScores,
     hostile::Score,
-    player::{self, Player},
+    modifier::Timed,
+    player::Player,
+    run::{RestartEvent, Seed},
+    save,
+    stats::RunStats,
+    upgrade::Upgrades,
     GameState,
 };
 
@@ -36,15 +49,21 @@ pub const ORANGE: Color = Color(255, 101, 66);
 pub const BLUE: Color = Color(29, 47, 111);
 pub const OFFWHITE: Color = Color(231, 236, 239);
 pub const YELLOW: Color = Color(248, 243, 43);
+pub const GREEN: Color = Color(82, 196, 104);
 
 pub struct Plugin;
 impl prelude::Plugin for Plugin {
     fn build(&self, app: &mut App) {
         app.add_event::<UiClickedEvent>()
             .add_event::<UpgradeSelectedEvent>()
+            .add_system(main_menu.in_set(OnUpdate(GameState::MainMenu)))
             .add_system(select_power.in_set(OnUpdate(GameState::LevelUp)))
-            .add_system(health)
-            .add_system(score)
+            .add_system(health.run_if(not(in_state(GameState::MainMenu))))
+            .add_system(score.run_if(not(in_state(GameState::MainMenu))))
+            .add_system(timed.run_if(not(in_state(GameState::MainMenu))))
+            .add_system(abilities.run_if(not(in_state(GameState::MainMenu))))
+            .add_system(toggle_pause)
+            .add_system(pause.in_set(OnUpdate(GameState::Paused)))
             .add_system(restart.in_set(OnUpdate(GameState::Dead)));
     }
 }
@@ -60,15 +79,210 @@ fn score(mut contexts: EguiContexts, score: Res<Score>) {
     });
 }
 
-fn restart(
+/// Buffs and debuffs running out on the player
+fn timed(mut contexts: EguiContexts, player: Query<&Timed, With<Player>>) {
+    let Ok(timed) = player.get_single() else {
+        return;
+    };
+    if timed.0.is_empty() {
+        return;
+    }
+
+    egui::Area::new("timed")
+        .anchor(egui::Align2::RIGHT_TOP, (-10., 10.))
+        .show(contexts.ctx_mut(), |ui| {
+            for (modifier, remaining) in timed.0.iter() {
+                let color = if modifier.op.is_negative() {
+                    RED
+                } else {
+                    GREEN
+                };
+                let text = format!(
+                    "{} {} ({:.0}s)",
+                    modifier.affect,
+                    modifier.op,
+                    remaining.as_secs_f32().ceil()
+                );
+                ui.label(egui::RichText::new(text).color(color).strong());
+            }
+        });
+}
+
+/// Ability slots with their keys and cooldowns, bottom-left
+fn abilities(mut contexts: EguiContexts, player: Query<&Slots, With<Player>>) {
+    let Ok(slots) = player.get_single() else {
+        return;
+    };
+
+    egui::Area::new("abilities")
+        .anchor(egui::Align2::LEFT_BOTTOM, (10., -10.))
+        .show(contexts.ctx_mut(), |ui| {
+            for (slot, key) in slots.0.iter().zip(ability::KEYS) {
+                let (text, progress) = if slot.cooldown.finished() {
+                    (format!("[{key:?}] {}", slot.ability), 1.)
+                } else {
+                    let remaining = slot.cooldown.remaining_secs();
+                    (
+                        format!("[{key:?}] {} {remaining:.1}s", slot.ability),
+                        slot.cooldown.percent(),
+                    )
+                };
+                ui.add(
+                    egui::ProgressBar::new(progress)
+                        .desired_width(200.)
+                        .text(text)
+                        .fill(BLUE.into()),
+                );
+            }
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn main_menu(
+    mut contexts: EguiContexts,
+    mut restart_writer: EventWriter<RestartEvent>,
+    mut load_writer: EventWriter<save::LoadEvent>,
+    save_exists: Res<save::SaveExists>,
+    mut volume: ResMut<Volume>,
+    mut exit_writer: EventWriter<AppExit>,
+    mut writer: EventWriter<UiClickedEvent>,
+    mut settings_open: Local<bool>,
+) {
+    egui::Area::new("main_menu").show(contexts.ctx_mut(), |ui| {
+        ui.with_layout(
+            egui::Layout::centered_and_justified(egui::Direction::TopDown),
+            |ui| {
+                egui::Frame::dark_canvas(ui.style()).show(ui, |ui| {
+                    ui.set_height(700.);
+                    ui.set_width(700.);
+
+                    ui.scope(|ui| {
+                        ui.style_mut().override_text_style = Some(egui::TextStyle::Heading);
+                        ui.visuals_mut().override_text_color = Some(OFFWHITE.into());
+
+                        ui.label(if *settings_open {
+                            "Settings"
+                        } else {
+                            "Bevy Jam 3"
+                        });
+                    });
+
+                    if *settings_open {
+                        let mut value = volume.0;
+                        if ui
+                            .add(egui::Slider::new(&mut value, 0.0..=2.0).text("Volume"))
+                            .changed()
+                        {
+                            volume.0 = value;
+                        }
+
+                        if ui.button("Back").clicked() {
+                            *settings_open = false;
+                            writer.send(UiClickedEvent);
+                        }
+                        return;
+                    }
+
+                    if save_exists.0 && ui.button("Continue").clicked() {
+                        load_writer.send(save::LoadEvent);
+                        writer.send(UiClickedEvent);
+                    }
+
+                    if ui.button("Start").clicked() {
+                        restart_writer.send(RestartEvent);
+                        writer.send(UiClickedEvent);
+                    }
+
+                    if ui.button("Settings").clicked() {
+                        *settings_open = true;
+                        writer.send(UiClickedEvent);
+                    }
+
+                    if ui.button("Quit").clicked() {
+                        exit_writer.send(AppExit);
+                    }
+                });
+            },
+        );
+    });
+}
+
+/// Toggle pause with Escape
+fn toggle_pause(
+    keyboard_input: Res<Input<KeyCode>>,
+    game_state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Escape) {
+        return;
+    }
+
+    match game_state.0 {
+        GameState::Game => next_state.set(GameState::Paused),
+        GameState::Paused => next_state.set(GameState::Game),
+        _ => {}
+    }
+}
+
+fn pause(
     mut contexts: EguiContexts,
-    player: Query<Entity, With<Player>>,
-    mut commands: Commands,
     mut game_state: ResMut<NextState<GameState>>,
-    mut score: ResMut<Score>,
+    mut restart_writer: EventWriter<RestartEvent>,
+    mut save_writer: EventWriter<save::SaveEvent>,
+    mut exit_writer: EventWriter<AppExit>,
+    mut writer: EventWriter<UiClickedEvent>,
+) {
+    egui::Area::new("pause").show(contexts.ctx_mut(), |ui| {
+        ui.with_layout(
+            egui::Layout::centered_and_justified(egui::Direction::TopDown),
+            |ui| {
+                egui::Frame::dark_canvas(ui.style()).show(ui, |ui| {
+                    ui.set_height(700.);
+                    ui.set_width(700.);
+
+                    ui.scope(|ui| {
+                        ui.style_mut().override_text_style = Some(egui::TextStyle::Heading);
+                        ui.visuals_mut().override_text_color = Some(OFFWHITE.into());
+
+                        ui.label("Paused");
+                    });
+
+                    if ui.button("Resume").clicked() {
+                        game_state.set(GameState::Game);
+                        writer.send(UiClickedEvent);
+                    }
+
+                    if ui.button("Restart").clicked() {
+                        restart_writer.send(RestartEvent);
+                        writer.send(UiClickedEvent);
+                    }
+
+                    if ui.button("Save & Quit").clicked() {
+                        save_writer.send(save::SaveEvent);
+                        exit_writer.send(AppExit);
+                    }
+
+                    if ui.button("Quit").clicked() {
+                        exit_writer.send(AppExit);
+                    }
+                });
+            },
+        );
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn restart(
+    mut contexts: EguiContexts,
+    seed: Res<Seed>,
+    score: Res<Score>,
+    scores: Res<HighScores>,
+    stats: Res<RunStats>,
+    upgrades: Res<Upgrades>,
+    balance: Res<Balance>,
+    mut restart_writer: EventWriter<RestartEvent>,
     mut writer: EventWriter<UiClickedEvent>,
 ) {
-    let player = player.single();
     egui::Area::new("death").show(contexts.ctx_mut(), |ui| {
         ui.with_layout(
             egui::Layout::centered_and_justified(egui::Direction::TopDown),
@@ -84,11 +298,13 @@ fn restart(
                         ui.label("You died!");
                     });
 
+                    ui.label(format!("Seed: {}", seed.0));
+
+                    summary(ui, &score, &stats, &upgrades, &balance);
+                    high_scores(ui, &scores);
+
                     if ui.button("Restart").clicked() {
-                        commands.entity(player).despawn_recursive();
-                        player::spawn(commands);
-                        game_state.set(GameState::Game);
-                        score.0 = 0;
+                        restart_writer.send(RestartEvent);
                         writer.send(UiClickedEvent);
                     };
                 });
@@ -97,8 +313,106 @@ fn restart(
     });
 }
 
+/// Minutes and seconds, e.g. `3:07`
+fn clock(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+fn summary(
+    ui: &mut egui::Ui,
+    score: &Score,
+    stats: &RunStats,
+    upgrades: &Upgrades,
+    balance: &Balance,
+) {
+    egui::Grid::new("summary").show(ui, |ui| {
+        let rows = [
+            ("Score", score.0.to_string()),
+            ("Time survived", clock(stats.survived)),
+            ("Level", stats.level().to_string()),
+            ("Kills", stats.kills.to_string()),
+            ("Shots fired", stats.shots.to_string()),
+            ("Damage dealt", stats.damage_dealt.to_string()),
+            ("Damage taken", stats.damage_taken.to_string()),
+        ];
+        for (name, value) in rows {
+            ui.label(name);
+            ui.label(value);
+            ui.end_row();
+        }
+    });
+
+    if !stats.upgrades.is_empty() {
+        ui.separator();
+        ui.label("Upgrades");
+        ui.horizontal_wrapped(|ui| {
+            for diff in stats.upgrades.iter() {
+                let text = format!(
+                    "{} {}",
+                    upgrades.name(diff.affect),
+                    diff.affect.op(diff.value, balance)
+                );
+                let color = if diff.value < 0. {
+                    egui::Color32::RED
+                } else {
+                    egui::Color32::GREEN
+                };
+                ui.label(egui::RichText::new(text).color(color));
+            }
+        });
+    }
+
+    if !stats.abilities.is_empty() {
+        ui.separator();
+        ui.label("Abilities");
+        ui.horizontal_wrapped(|ui| {
+            for ability in stats.abilities.iter() {
+                ui.label(egui::RichText::new(ability.to_string()).color(egui::Color32::LIGHT_BLUE));
+            }
+        });
+    }
+}
+
+fn high_scores(ui: &mut egui::Ui, scores: &HighScores) {
+    if scores.entries.is_empty() {
+        return;
+    }
+
+    ui.separator();
+    ui.label("High Scores");
+    egui::Grid::new("high_scores").striped(true).show(ui, |ui| {
+        for header in ["#", "Score", "Time", "Level", "Seed", "Date"] {
+            ui.label(header);
+        }
+        ui.end_row();
+
+        for (index, entry) in scores.entries.iter().enumerate() {
+            let cells = [
+                format!("{}", index + 1),
+                entry.score.to_string(),
+                clock(entry.survived),
+                entry.level.to_string(),
+                entry.seed.to_string(),
+                entry.date.clone(),
+            ];
+
+            for cell in cells {
+                let mut text = egui::RichText::new(cell);
+                if scores.latest == Some(index) {
+                    text = text.color(YELLOW).strong();
+                }
+                ui.label(text);
+            }
+            ui.end_row();
+        }
+    });
+}
+
 fn health(mut contexts: EguiContexts, player: Query<(&Health, &MaxHealth), With<Player>>) {
-    let (health, max) = player.single();
+    let Ok((health, max)) = player.get_single() else {
+        return;
+    };
     egui::Area::new("health").show(contexts.ctx_mut(), |ui| {
         ui.with_layout(egui::Layout::bottom_up(egui::Align::Center), |ui| {
             ui.set_height(500.);
@@ -114,26 +428,17 @@ fn health(mut contexts: EguiContexts, player: Query<(&Health, &MaxHealth), With<
     });
 }
 
+#[allow(clippy::too_many_arguments)]
 fn select_power(
-    player: Query<Entity, With<Player>>,
     mut contexts: EguiContexts,
-    mut writer: EventWriter<buff::Apply>,
-    mut choices: ResMut<buff::Choices>,
-    mut game_state: ResMut<NextState<GameState>>,
-    mut rng: ResMut<GlobalRng>,
+    choices: Res<buff::Choices>,
+    upgrades: Res<Upgrades>,
+    balance: Res<Balance>,
+    player: Query<&Slots, With<Player>>,
+    mut writer: EventWriter<buff::Select>,
+    mut learn_writer: EventWriter<ability::Learn>,
     mut update_selected_writer: EventWriter<UpgradeSelectedEvent>,
 ) {
-    if choices.remaining == 0 {
-        game_state.set(GameState::Game);
-        return;
-    }
-
-    if choices.inner.is_empty() {
-        choices.randomize(3, &mut rng);
-    }
-
-    let player = player.single();
-    let mut remaining = choices.remaining;
     egui::Area::new("levelup").show(contexts.ctx_mut(), |ui| {
         ui.with_layout(
             egui::Layout::centered_and_justified(egui::Direction::LeftToRight),
@@ -141,42 +446,56 @@ fn select_power(
                 ui.set_height(500.);
                 ui.set_width(900.);
 
-                ui.horizontal(|ui| {
-                    for (positive, negative) in choices.inner.iter() {
-                        let text = format!(
-                            "Improve {} by +{:.2}% \n\n\n\n Degrade {} by {:.2}%",
-                            positive.affect,
-                            positive.value * 100.,
-                            negative.affect,
-                            negative.value * 100.
-                        );
+                ui.vertical(|ui| {
+                    ui.horizontal(|ui| {
+                        for (index, (positive, negative)) in choices.inner.iter().enumerate() {
+                            let text = format!(
+                                "Improve {} by {} \n\n\n\n Degrade {} by {}",
+                                upgrades.name(positive.affect),
+                                positive.affect.op(positive.value, &balance),
+                                upgrades.name(negative.affect),
+                                negative.affect.op(negative.value, &balance)
+                            );
 
-                        if ui
-                            .add_sized((300., 150.), egui::Button::new(text).wrap(true))
-                            .clicked()
-                        {
-                            remaining = remaining.saturating_sub(1);
-                            writer.send_batch([
-                                buff::Apply {
-                                    diff: *positive,
-                                    target: player,
-                                },
-                                buff::Apply {
-                                    diff: *negative,
-                                    target: player,
-                                },
-                            ]);
-                            update_selected_writer.send(UpgradeSelectedEvent);
+                            if ui
+                                .add_sized((300., 150.), egui::Button::new(text).wrap(true))
+                                .clicked()
+                            {
+                                writer.send(buff::Select(index));
+                                update_selected_writer.send(UpgradeSelectedEvent);
+                            }
                         }
-                    }
+                    });
+
+                    // Learning an ability takes the place of picking a pair
+                    let (Some(offered), Ok(slots)) = (choices.ability, player.get_single()) else {
+                        return;
+                    };
+                    ui.horizontal(|ui| {
+                        let mut learn = |ui: &mut egui::Ui, text: String, slot| {
+                            if ui
+                                .add_sized((300., 50.), egui::Button::new(text).wrap(true))
+                                .clicked()
+                            {
+                                learn_writer.send(ability::Learn(slot));
+                                update_selected_writer.send(UpgradeSelectedEvent);
+                            }
+                        };
+
+                        let held = slots.0.len();
+                        if let Some(key) = ability::KEYS.get(held) {
+                            learn(ui, format!("Learn {offered} [{key:?}]"), held);
+                        }
+                        for (index, slot) in slots.0.iter().enumerate() {
+                            learn(
+                                ui,
+                                format!("Replace {} with {offered}", slot.ability),
+                                index,
+                            );
+                        }
+                    });
                 });
             },
         );
     });
-
-    if choices.remaining != remaining {
-        choices.randomize(3, &mut rng);
-    }
-
-    choices.remaining = remaining;
 }
@@ -0,0 +1,199 @@
+use std::ops::Range;
+
+use bevy::{
+    prelude::{self, *},
+    reflect::TypeUuid,
+};
+use bevy_turborand::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use crate::{
+    buff::{Affect, Diff},
+    ron_asset::{self, RonAsset},
+};
+
+pub struct Plugin;
+impl prelude::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(ron_asset::Plugin::<Upgrades>::default());
+    }
+}
+
+/// Every upgrade a level up can offer, read from `assets/game.upgrades.ron` and reloaded
+/// whenever the file changes
+#[derive(Debug, Clone, Resource, Serialize, Deserialize, TypeUuid)]
+#[uuid = "0f4e3c1d-8b7a-4d52-a1e6-93c2b5d7f810"]
+pub struct Upgrades(pub Vec<Upgrade>);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Upgrade {
+    pub affect: Affect,
+    /// Shown on the level up screen, the attribute's own name when empty
+    #[serde(default)]
+    pub name: String,
+    /// Magnitudes rolled, see [`Affect::op`]
+    #[serde(default = "default_values")]
+    pub values: Range<f32>,
+    /// Odds of being offered relative to the other upgrades
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+    /// Attributes the debuff paired with this upgrade may degrade. When empty, any other
+    /// the player doesn't start at zero in, see [`Affect::starts_at_zero`].
+    #[serde(default)]
+    pub pairs: Vec<Affect>,
+}
+
+fn default_values() -> Range<f32> {
+    0.05..0.5
+}
+
+fn default_weight() -> f32 {
+    1.
+}
+
+impl Default for Upgrades {
+    fn default() -> Self {
+        Self(
+            Affect::iter()
+                .map(|affect| Upgrade {
+                    affect,
+                    name: String::new(),
+                    values: default_values(),
+                    weight: default_weight(),
+                    pairs: Vec::new(),
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Upgrades {
+    /// Name shown for upgrades of `affect`
+    pub fn name(&self, affect: Affect) -> String {
+        self.0
+            .iter()
+            .find(|upgrade| upgrade.affect == affect && !upgrade.name.is_empty())
+            .map_or_else(|| affect.to_string(), |upgrade| upgrade.name.clone())
+    }
+
+    /// Whether there are at least two attributes to pair a buff and a debuff from
+    pub fn is_rollable(&self) -> bool {
+        let mut affects = self.0.iter().filter(|upgrade| upgrade.weight > 0.);
+        let Some(first) = affects.next() else {
+            return false;
+        };
+        affects.any(|upgrade| upgrade.affect != first.affect)
+    }
+
+    /// A buff and the debuff paired with it, `None` when no pair can be made
+    pub fn roll(&self, rng: &mut GlobalRng) -> Option<(Diff, Diff)> {
+        let buff = pick(rng, self.0.iter())?;
+        let debuff = pick(
+            rng,
+            self.0.iter().filter(|debuff| {
+                debuff.affect != buff.affect
+                    && if buff.pairs.is_empty() {
+                        !debuff.affect.starts_at_zero()
+                    } else {
+                        buff.pairs.contains(&debuff.affect)
+                    }
+            }),
+        )?;
+        Some((buff.roll(rng), -debuff.roll(rng)))
+    }
+}
+
+impl Upgrade {
+    fn roll(&self, rng: &mut GlobalRng) -> Diff {
+        let Range { start, end } = self.values;
+        let value = start + rng.f32() * (end - start);
+        Diff {
+            affect: self.affect,
+            // Whole percents read better on the level up screen
+            value: (value * 100.).round() / 100.,
+        }
+    }
+}
+
+/// One of `upgrades` at random, by weight
+fn pick<'a>(
+    rng: &mut GlobalRng,
+    upgrades: impl Iterator<Item = &'a Upgrade>,
+) -> Option<&'a Upgrade> {
+    let upgrades: Vec<_> = upgrades.filter(|upgrade| upgrade.weight > 0.).collect();
+    let max = upgrades
+        .iter()
+        .map(|upgrade| upgrade.weight)
+        .fold(0., f32::max);
+    rng.weighted_sample(&upgrades, |(upgrade, _)| f64::from(upgrade.weight / max))
+        .copied()
+}
+
+impl RonAsset for Upgrades {
+    const PATH: &'static str = "game.upgrades.ron";
+
+    fn validate(&self) -> Result<(), String> {
+        if self.is_rollable() {
+            Ok(())
+        } else {
+            Err("upgrades need two attributes with a positive weight to pair".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_upgrades_parse() {
+        let upgrades: Upgrades =
+            ron::from_str(include_str!("../assets/game.upgrades.ron")).unwrap();
+        assert_eq!(upgrades.0.len(), Upgrades::default().0.len());
+        assert!(upgrades.is_rollable());
+    }
+
+    #[test]
+    fn debuffs_respect_pairs() {
+        let mut rng = GlobalRng::with_seed(3);
+        let upgrades = Upgrades(vec![
+            Upgrade {
+                affect: Affect::Damage,
+                name: "Sharpness".into(),
+                values: 0.1..0.2,
+                weight: 1.,
+                pairs: vec![Affect::Armor],
+            },
+            Upgrade {
+                affect: Affect::Armor,
+                name: String::new(),
+                values: 0.1..0.2,
+                weight: 1.,
+                pairs: Vec::new(),
+            },
+            Upgrade {
+                affect: Affect::MoveSpeed,
+                name: String::new(),
+                values: 0.1..0.2,
+                weight: 1.,
+                pairs: Vec::new(),
+            },
+        ]);
+
+        for _ in 0..50 {
+            let (buff, debuff) = upgrades.roll(&mut rng).unwrap();
+            assert!(buff.value >= 0.1 && buff.value <= 0.2);
+            assert!(debuff.value < 0.);
+            if buff.affect == Affect::Damage {
+                assert_eq!(debuff.affect, Affect::Armor);
+            } else {
+                // Armor starts at zero and is only degraded when paired explicitly
+                assert_ne!(debuff.affect, Affect::Armor);
+            }
+        }
+        assert_eq!(upgrades.name(Affect::Damage), "Sharpness");
+        assert_eq!(upgrades.name(Affect::MoveSpeed), "Movement Speed");
+        assert!(!Upgrades(upgrades.0[..1].to_vec()).is_rollable());
+    }
+}
@@ -0,0 +1,90 @@
+use bevy::{ecs::system::CommandQueue, prelude::*};
+use bevy_jam_3::{
+    attribute::Health,
+    balance::Balance,
+    headless_app,
+    hostile::{self, HitStun, Hostile},
+    player::Player,
+    run::SeedConfig,
+    stats::RunStats,
+    GameState,
+};
+use bevy_rapier2d::prelude::Velocity;
+
+#[test]
+fn idle_player_is_overrun() {
+    let mut app = headless_app();
+
+    let mut state = GameState::Game;
+    for _ in 0..5_000 {
+        app.update();
+        state = app.world.resource::<State<GameState>>().0;
+        if state == GameState::Dead {
+            break;
+        }
+    }
+
+    assert_eq!(state, GameState::Dead);
+    let mut player = app.world.query_filtered::<&Health, With<Player>>();
+    assert!(player.single(&app.world).0 <= 0);
+    let mut hostiles = app.world.query_filtered::<(), With<Hostile>>();
+    assert_eq!(hostiles.iter(&app.world).count(), 0);
+    let stats = app.world.resource::<RunStats>();
+    assert!(stats.damage_taken >= 100);
+    assert!(!stats.survived.is_zero());
+}
+
+#[test]
+fn same_seed_same_run() {
+    let positions = || {
+        let mut app = headless_app();
+        app.insert_resource(SeedConfig(Some(7)));
+
+        let mut positions = Vec::new();
+        for _ in 0..600 {
+            app.update();
+            let mut hostiles = app.world.query_filtered::<&Transform, With<Hostile>>();
+            positions.extend(hostiles.iter(&app.world).map(|t| t.translation));
+        }
+        positions
+    };
+
+    let first = positions();
+    assert!(!first.is_empty());
+    assert_eq!(first, positions());
+}
+
+#[test]
+fn hits_knock_hostiles_along_the_shot() {
+    let mut app = headless_app();
+    app.insert_resource(SeedConfig(Some(5)));
+    let mut player = app.world.query_filtered::<&Transform, With<Player>>();
+    while player.iter(&app.world).next().is_none() {
+        app.update();
+    }
+
+    let origin = player.single(&app.world).translation;
+    let stats = app.world.resource::<Balance>().hostile.clone();
+    let mut queue = CommandQueue::default();
+    let target = hostile::spawn_at(
+        &mut Commands::new(&mut queue, &app.world),
+        origin + Vec3::new(150., 0., 0.),
+        &stats,
+    );
+    queue.apply(&mut app.world);
+
+    for _ in 0..60 {
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::Right);
+        app.update();
+
+        if app.world.get::<HitStun>(target).is_some() {
+            // Chasing the player on its left until pushed the way the shot flew
+            let velocity = app.world.get::<Velocity>(target).unwrap().linvel;
+            assert!(velocity.x > 0., "not knocked along the shot: {velocity}");
+            return;
+        }
+    }
+    panic!("Hostile was never hit");
+}
@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+use bevy_jam_3::{
+    attribute::{Damage, Experience, MaxHealth, MoveSpeed},
+    buff, headless_app,
+    hostile::Score,
+    player::Player,
+    replay,
+    run::SeedConfig,
+    GameState,
+};
+
+/// Frames between two levels granted to the player
+const LEVEL_EVERY: usize = 300;
+
+#[derive(Debug, PartialEq)]
+struct Run {
+    score: u128,
+    frames: usize,
+    level_up_frames: usize,
+    damage: i32,
+    max_health: i32,
+    move_speed: f32,
+}
+
+/// Play until death, spinning the aim and filling the experience bar every
+/// [`LEVEL_EVERY`] frames. While `picking`, every level up takes the first upgrade and
+/// the second in the same frame, otherwise the replay picks.
+fn play_to_death(app: &mut App, picking: bool) -> Run {
+    let aim = [KeyCode::Right, KeyCode::Down, KeyCode::Left, KeyCode::Up];
+    let mut level_up_frames = 0;
+    for frame in 0..20_000 {
+        let mut keys = app.world.resource_mut::<Input<KeyCode>>();
+        keys.release_all();
+        keys.press(aim[frame / 10 % aim.len()]);
+
+        if frame % LEVEL_EVERY == LEVEL_EVERY - 1 {
+            let mut player = app.world.query_filtered::<&mut Experience, With<Player>>();
+            if let Ok(mut experience) = player.get_single_mut(&mut app.world) {
+                experience.current = experience.cap;
+            }
+        }
+
+        app.update();
+        match app.world.resource::<State<GameState>>().0 {
+            GameState::LevelUp => {
+                level_up_frames += 1;
+                if picking {
+                    app.world.send_event(buff::Select(0));
+                    app.world.send_event(buff::Select(1));
+                }
+            }
+            GameState::Dead => {
+                let mut player = app
+                    .world
+                    .query_filtered::<(&Damage, &MaxHealth, &MoveSpeed), With<Player>>();
+                let (damage, max_health, move_speed) = player.single(&app.world);
+                return Run {
+                    score: app.world.resource::<Score>().0,
+                    frames: frame,
+                    level_up_frames,
+                    damage: damage.0,
+                    max_health: max_health.0,
+                    move_speed: move_speed.0,
+                };
+            }
+            _ => {}
+        }
+    }
+    panic!("Run never ended");
+}
+
+#[test]
+fn replay_matches_recording() {
+    // Unique to this test and process so parallel runs don't share the file
+    let path = std::env::temp_dir().join(format!(
+        "bevy-jam-3-replay_matches_recording-{}.ron",
+        std::process::id()
+    ));
+
+    let mut app = headless_app();
+    app.insert_resource(SeedConfig(Some(3)));
+    replay::record(&mut app, &path);
+    let recorded = play_to_death(&mut app, true);
+    assert!(recorded.level_up_frames > 0);
+
+    let mut app = headless_app();
+    replay::replay(&mut app, &path).unwrap();
+    assert_eq!(play_to_death(&mut app, false), recorded);
+    let _ = std::fs::remove_file(&path);
+}